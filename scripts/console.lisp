@@ -1,8 +1,9 @@
 (use random (random))
+(use text (maptext))
 
 (struct Console ((s string) (toggle bool)))
 
-(define (state-init)
+(define (state-init args)
   (new Console :s "" :toggle false)
 )
 
@@ -26,13 +27,6 @@
   state)
 )
 
-(define (maptext f state)
-  (let ((init (. state :s)))
-    (.= state :s (f init))
-  )
-)
-
-
 (define (toggle-console state)
   (let (
     (init-toggle (. state :toggle))
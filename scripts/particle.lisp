@@ -3,7 +3,7 @@
 
 (struct Part ((x float) (y float) (t float)))
 
-(define (state-init)
+(define (state-init args)
   (let 
     ((pids (get-ids "player")))
     (if (null pids)
@@ -12,8 +12,9 @@
         (destroy me)
       )
       (let (
-           (x (. (get (first pids)) :x) )
-           (y (. (get (first pids)) :y) )
+           (pos (position (first pids)))
+           (x (first pos))
+           (y (second pos))
          )
          (do
           ;(println "MAKING")
@@ -29,7 +30,7 @@
 
 (define (tick state) 
   (if (null state)
-    (state-init)
+    (state-init ())
     (let
       (
        (x0 (. state :x))
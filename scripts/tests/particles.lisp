@@ -0,0 +1,32 @@
+; Spawns thousands of particles, which destroy themselves at random, to
+; check dead objects are forgotten, driven by particles.json
+
+(const COUNT 3000)
+(const PER-TICK 500)
+
+(struct Spawner ((ids list)))
+
+(define (spawn n ids)
+  (if (= n 0)
+    ids
+    (spawn (- n 1) (cons (create 0.0 0.0 "particle") ids))))
+
+(define (state-init args) (new Spawner :ids ()))
+
+(define (tick state)
+  (if (< (len (. state :ids)) COUNT)
+    (.= state :ids (spawn PER-TICK (. state :ids)))
+    state))
+
+; Every id has state, so get-ids isn't handing out dead ones
+(define (all-live ids)
+  (cond
+    ((null ids) true)
+    ((null (get (first ids))) false)
+    (else (all-live (tail ids)))))
+
+(define (all-dead ids)
+  (cond
+    ((null ids) true)
+    ((null (get (first ids))) (all-dead (tail ids)))
+    (else false)))
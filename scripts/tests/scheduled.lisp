@@ -0,0 +1,18 @@
+; Exercises after, every, tween and sequence, driven by scheduled.json
+; The first tick only runs state-init, timers start on the second
+
+(struct Sched ((fired integer) (ticks integer) (alpha float) (steps list)))
+
+(define (state-init args)
+  (do
+    (after 0.5 "fire")
+    (every 0.25 "count")
+    (tween "fade" 0.0 1.0 1.0)
+    (sequence (list "step-a" (wait 0.5) "step-b"))
+    (new Sched :fired 0 :ticks 0 :alpha 0.0 :steps ())))
+
+(define (fire state) (.= state :fired (+ (. state :fired) 1)))
+(define (count state) (.= state :ticks (+ (. state :ticks) 1)))
+(define (fade state value) (.= state :alpha value))
+(define (step-a state) (.= state :steps (append (. state :steps) "a")))
+(define (step-b state) (.= state :steps (append (. state :steps) "b")))
@@ -0,0 +1,544 @@
+use descriptors::{error_simple, get_string};
+use draw::SpriteBatch;
+use game::fphys;
+use graphics::DrawState;
+use graphics::math::Matrix2d;
+use opengl_graphics::GlGraphics;
+use rustc_serialize::json::{Json, Object};
+use sprite_sheet::Animation;
+use std::cmp;
+use std::collections::HashMap;
+use std::io::Error;
+use std::rc::Rc;
+
+/*
+ * A state machine choosing which animation an object shows, shared by the
+ * player, enemies and scripted objects
+ *
+ * Described by an "animator" object in a descriptor:
+ *   "initial"      state shown first
+ *   "states"       {name: {"animation": name of one of the object's animations,
+ *                          "rate": playback speed, a number or a parameter,
+ *                          "one_shot": play once instead of looping,
+ *                          "then": state to go to once a one shot is done,
+ *                          "events": {event: [frame, ..]}}}
+ *   "transitions"  [{"from": a state or list of them, any state if left out,
+ *                    "to": state,
+ *                    "when": {param: true, param: 2, param: {"gt": 3}, ..},
+ *                    "blend": seconds to fade out the old animation over,
+ *                    "interrupt": whether it can cut a one shot short}]
+ *
+ * Parameters are numbers the owner sets every tick, true and false are
+ * stored as 1 and 0. Transitions are tried in order and the first whose
+ * conditions all hold wins, so earlier ones take priority. If that is a
+ * transition to the current state nothing changes.
+ *
+ * An animation name can hold a parameter in braces, eg. "aiming{weapon}"
+ * shows "aiming1" while weapon is 1. Events fire as their frame is reached,
+ * frames counting from 0 in the order the animation plays them.
+ */
+
+//  Animations an object has, by name
+pub type AnimationSet = HashMap<String, Animation>;
+
+enum Rate {
+    Fixed(fphys),
+    Param(String),
+}
+
+#[derive(Clone, Copy)]
+enum Compare {
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+}
+
+const COMPARISONS: [(&'static str, Compare); 6] = [("gt", Compare::Gt),
+                                                   ("lt", Compare::Lt),
+                                                   ("ge", Compare::Ge),
+                                                   ("le", Compare::Le),
+                                                   ("eq", Compare::Eq),
+                                                   ("ne", Compare::Ne)];
+
+struct Condition {
+    param: String,
+    compare: Compare,
+    value: fphys,
+}
+
+pub struct AnimState {
+    pub animation: String,
+    rate: Rate,
+    pub one_shot: bool,
+    pub then: Option<String>,
+    //  Frame position and the event fired on reaching it
+    events: Vec<(usize, String)>,
+}
+
+struct Transition {
+    //  Empty for any state
+    from: Vec<String>,
+    to: String,
+    when: Vec<Condition>,
+    blend: fphys,
+    interrupt: bool,
+}
+
+pub struct AnimatorDescriptor {
+    pub initial: String,
+    pub states: HashMap<String, AnimState>,
+    transitions: Vec<Transition>,
+}
+
+fn parse_conditions(dname: &str, when: &Object) -> Result<Vec<Condition>, Error> {
+    let mut conditions = Vec::new();
+    for (param, test) in when {
+        let condition = |compare, value| {
+            Condition {
+                param: param.clone(),
+                compare: compare,
+                value: value,
+            }
+        };
+        match *test {
+            Json::Boolean(b) => {
+                conditions.push(condition(if b { Compare::Ne } else { Compare::Eq }, 0.0));
+            }
+            Json::Object(ref tests) => {
+                for (op, value) in tests {
+                    let compare = COMPARISONS.iter()
+                        .find(|c| c.0 == op.as_str())
+                        .map(|c| c.1)
+                        .ok_or(error_simple(dname,
+                                            &format!("unknown comparison '{}' for {}",
+                                                     op,
+                                                     param)))?;
+                    let value = value.as_f64()
+                        .ok_or(error_simple(dname,
+                                            &format!("{} {} needs a number", param, op)))?;
+                    conditions.push(condition(compare, value));
+                }
+            }
+            ref value => {
+                let value = value.as_f64()
+                    .ok_or(error_simple(dname,
+                                        &format!("condition on {} is not a bool, number \
+                                                  or object",
+                                                 param)))?;
+                conditions.push(condition(Compare::Eq, value));
+            }
+        }
+    }
+    Ok(conditions)
+}
+
+fn parse_state(dname: &str, name: &str, obj: &Object) -> Result<AnimState, Error> {
+    let rate = match obj.get("rate") {
+        None => Rate::Fixed(1.0),
+        Some(&Json::String(ref param)) => Rate::Param(param.clone()),
+        Some(value) => {
+            Rate::Fixed(value.as_f64()
+                .ok_or(error_simple(dname,
+                                    &format!("rate of {} is not a number or parameter",
+                                             name)))?)
+        }
+    };
+    let mut events = Vec::new();
+    if let Some(events_obj) = obj.get("events").and_then(|e| e.as_object()) {
+        for (event, frames) in events_obj {
+            let frames = frames.as_array()
+                .ok_or(error_simple(dname,
+                                    &format!("frames for event {} are not an array",
+                                             event)))?;
+            for frame in frames {
+                let frame = frame.as_u64()
+                    .ok_or(error_simple(dname,
+                                        &format!("event {} has a bad frame", event)))?;
+                events.push((frame as usize, event.clone()));
+            }
+        }
+    }
+    Ok(AnimState {
+        animation: get_string(dname, obj, "animation")?,
+        rate: rate,
+        one_shot: obj.get("one_shot").and_then(|o| o.as_boolean()).unwrap_or(false),
+        then: obj.get("then").and_then(|t| t.as_string()).map(|t| t.to_owned()),
+        events: events,
+    })
+}
+
+fn parse_transition(dname: &str, obj: &Object) -> Result<Transition, Error> {
+    let from = match obj.get("from") {
+        None => Vec::new(),
+        Some(&Json::String(ref state)) => vec![state.clone()],
+        Some(&Json::Array(ref states)) => {
+            states.iter()
+                .map(|s| {
+                    s.as_string()
+                        .map(|s| s.to_owned())
+                        .ok_or(error_simple(dname, "'from' must name states"))
+                })
+                .collect::<Result<Vec<String>, Error>>()?
+        }
+        Some(_) => return Err(error_simple(dname, "'from' must name states")),
+    };
+    let when = match obj.get("when") {
+        Some(&Json::Object(ref when)) => parse_conditions(dname, when)?,
+        Some(_) => return Err(error_simple(dname, "'when' is not an object")),
+        None => Vec::new(),
+    };
+    Ok(Transition {
+        from: from,
+        to: get_string(dname, obj, "to")?,
+        when: when,
+        blend: obj.get("blend").and_then(|b| b.as_f64()).unwrap_or(0.0),
+        interrupt: obj.get("interrupt").and_then(|i| i.as_boolean()).unwrap_or(false),
+    })
+}
+
+impl AnimatorDescriptor {
+    pub fn from_json(dname: &str, obj: &Object) -> Result<Rc<Self>, Error> {
+        let mut states = HashMap::new();
+        let states_obj = obj.get("states")
+            .and_then(|s| s.as_object())
+            .ok_or(error_simple(dname, "animator has no 'states' object"))?;
+        for (name, state) in states_obj {
+            let state = state.as_object()
+                .ok_or(error_simple(dname,
+                                    &format!("state {} not well formed", name)))?;
+            states.insert(name.clone(), parse_state(dname, name, state)?);
+        }
+
+        let mut transitions = Vec::new();
+        if let Some(array) = obj.get("transitions").and_then(|t| t.as_array()) {
+            for (i, transition) in array.iter().enumerate() {
+                let transition = transition.as_object()
+                    .ok_or(error_simple(dname,
+                                        &format!("transition {} not well formed", i)))?;
+                transitions.push(parse_transition(dname, transition)?);
+            }
+        }
+
+        let descr = AnimatorDescriptor {
+            initial: get_string(dname, obj, "initial")?,
+            states: states,
+            transitions: transitions,
+        };
+        descr.check_states(dname)?;
+        Ok(Rc::new(descr))
+    }
+
+    //  Every state named anywhere has to exist
+    fn check_states(&self, dname: &str) -> Result<(), Error> {
+        let named = self.states
+            .values()
+            .filter_map(|s| s.then.as_ref())
+            .chain(self.transitions.iter().flat_map(|t| t.from.iter()))
+            .chain(self.transitions.iter().map(|t| &t.to))
+            .chain(Some(&self.initial));
+        for name in named {
+            if !self.states.contains_key(name) {
+                return Err(error_simple(dname,
+                                        &format!("animator has no state '{}'", name)));
+            }
+        }
+        Ok(())
+    }
+
+    //  Every animation a state shows has to be in animations, those
+    //  depending on a parameter can only be checked as they are shown
+    pub fn check_animations(&self, dname: &str, animations: &AnimationSet) -> Result<(), Error> {
+        for (name, state) in &self.states {
+            if !state.animation.contains('{') && !animations.contains_key(&state.animation) {
+                return Err(error_simple(dname,
+                                        &format!("state {} shows unknown animation {}",
+                                                 name,
+                                                 state.animation)));
+            }
+        }
+        Ok(())
+    }
+
+    //  Whether any state fires event, so owners can wait for it
+    pub fn fires(&self, event: &str) -> bool {
+        self.states.values().any(|s| s.events.iter().any(|e| e.1 == event))
+    }
+}
+
+//  One frame to draw, alpha is below 1 while blending
+pub struct AnimLayer {
+    pub animation: String,
+    pub frame: usize,
+    pub alpha: f32,
+}
+
+//  The frame a state was on when it was left, faded out during a blend
+struct Fading {
+    animation: String,
+    frame: usize,
+    blend: fphys,
+    left: fphys,
+}
+
+pub struct Animator {
+    descr: Rc<AnimatorDescriptor>,
+    params: HashMap<String, fphys>,
+    state: String,
+    //  Seconds into the state's animation, scaled by its rate
+    time: fphys,
+    //  Set on entering a state, as frame 0's events haven't fired yet
+    entered: bool,
+    fading: Option<Fading>,
+}
+
+//  Frames reached so far, counting each repeat of a looping animation
+fn frames_reached(anim: &Animation, t: fphys, one_shot: bool) -> usize {
+    if one_shot && t >= anim.length {
+        anim.frames.len() - 1
+    } else if one_shot || anim.length <= 0.0 {
+        anim.frame_index_at(t)
+    } else {
+        (t / anim.length).floor() as usize * anim.frames.len() + anim.frame_index_at(t)
+    }
+}
+
+impl Animator {
+    pub fn new(descr: Rc<AnimatorDescriptor>) -> Self {
+        Animator {
+            state: descr.initial.clone(),
+            descr: descr,
+            params: HashMap::new(),
+            time: 0.0,
+            entered: true,
+            fading: None,
+        }
+    }
+
+    //  Swap in a reloaded descriptor, staying in the same state if it still exists
+    pub fn set_descriptor(&mut self, descr: Rc<AnimatorDescriptor>) {
+        if !descr.states.contains_key(&self.state) {
+            self.state = descr.initial.clone();
+            self.time = 0.0;
+            self.entered = true;
+            self.fading = None;
+        }
+        self.descr = descr;
+    }
+
+    pub fn set(&mut self, param: &str, value: fphys) {
+        if let Some(v) = self.params.get_mut(param) {
+            *v = value;
+            return;
+        }
+        self.params.insert(param.to_owned(), value);
+    }
+
+    pub fn set_bool(&mut self, param: &str, value: bool) {
+        self.set(param, if value { 1.0 } else { 0.0 });
+    }
+
+    pub fn param(&self, param: &str) -> fphys {
+        self.params.get(param).cloned().unwrap_or(0.0)
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    //  Jump straight to a state, ignoring transitions, false if there is none
+    pub fn play(&mut self, state: &str, blend: fphys, animations: &AnimationSet) -> bool {
+        if !self.descr.states.contains_key(state) {
+            return false;
+        }
+        self.enter(state.to_owned(), blend, animations);
+        true
+    }
+
+    fn current(&self) -> &AnimState {
+        &self.descr.states[&self.state]
+    }
+
+    //  Name of the animation state shows with any parameter filled in
+    fn animation_name(&self, state: &AnimState) -> String {
+        let name = &state.animation;
+        match (name.find('{'), name.find('}')) {
+            (Some(open), Some(close)) if open < close => {
+                let value = self.param(&name[open + 1..close]);
+                format!("{}{}{}", &name[..open], value.round() as i64, &name[close + 1..])
+            }
+            _ => name.clone(),
+        }
+    }
+
+    pub fn animation<'a>(&self, animations: &'a AnimationSet) -> Option<&'a Animation> {
+        animations.get(&self.animation_name(self.current()))
+    }
+
+    //  Position in its frames of the frame the current animation is on
+    fn frame(&self, anim: &Animation) -> usize {
+        if self.current().one_shot && self.time >= anim.length {
+            anim.frames.len() - 1
+        } else {
+            anim.frame_index_at(self.time)
+        }
+    }
+
+    fn finished(&self, animations: &AnimationSet) -> bool {
+        self.current().one_shot &&
+        self.animation(animations).map_or(true, |anim| self.time >= anim.length)
+    }
+
+    fn enter(&mut self, state: String, blend: fphys, animations: &AnimationSet) {
+        self.fading = if blend > 0.0 {
+            self.animation(animations).map(|anim| {
+                Fading {
+                    animation: self.animation_name(self.current()),
+                    frame: self.frame(anim),
+                    blend: blend,
+                    left: blend,
+                }
+            })
+        } else {
+            None
+        };
+        self.state = state;
+        self.time = 0.0;
+        self.entered = true;
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        let value = self.param(&condition.param);
+        match condition.compare {
+            Compare::Gt => value > condition.value,
+            Compare::Lt => value < condition.value,
+            Compare::Ge => value >= condition.value,
+            Compare::Le => value <= condition.value,
+            Compare::Eq => value == condition.value,
+            Compare::Ne => value != condition.value,
+        }
+    }
+
+    //  The state to move to and blend time, None to stay
+    //  Only interrupting transitions can leave a one shot before it's done
+    fn next_state(&self, finished: bool) -> Option<(String, fphys)> {
+        let locked = self.current().one_shot && !finished;
+        let taken = self.descr
+            .transitions
+            .iter()
+            .filter(|t| !locked || t.interrupt)
+            .filter(|t| t.from.is_empty() || t.from.contains(&self.state))
+            .find(|t| t.when.iter().all(|c| self.holds(c)));
+        match taken {
+            Some(t) if t.to != self.state => Some((t.to.clone(), t.blend)),
+            _ => None,
+        }
+    }
+
+    //  Advance dt seconds, returning the events of every frame reached
+    pub fn update(&mut self, dt: fphys, animations: &AnimationSet) -> Vec<String> {
+        let finished = self.finished(animations);
+        if let Some((state, blend)) = self.next_state(finished) {
+            self.enter(state, blend, animations);
+        }
+
+        if let Some(mut fading) = self.fading.take() {
+            fading.left -= dt;
+            if fading.left > 0.0 {
+                self.fading = Some(fading);
+            }
+        }
+
+        let rate = match self.current().rate {
+            Rate::Fixed(rate) => rate,
+            Rate::Param(ref param) => self.param(param),
+        };
+        let before = self.time;
+        self.time += dt * rate;
+
+        let mut events = Vec::new();
+        if let Some(anim) = self.animation(animations) {
+            let state = self.current();
+            let n = anim.frames.len();
+            let from = frames_reached(anim, before, state.one_shot);
+            let to = frames_reached(anim, self.time, state.one_shot);
+            //  However large dt was, each frame's events fire once at most
+            let first = cmp::max(if self.entered { from } else { from + 1 },
+                                 (to + 1).saturating_sub(n));
+            for reached in first..to + 1 {
+                events.extend(state.events
+                    .iter()
+                    .filter(|&&(frame, _)| frame == reached % n)
+                    .map(|&(_, ref event)| event.clone()));
+            }
+        }
+        self.entered = false;
+
+        if self.finished(animations) {
+            if let Some(then) = self.current().then.clone() {
+                self.enter(then, 0.0, animations);
+            }
+        }
+        events
+    }
+
+    //  What to draw, the current animation then any being faded out over it
+    pub fn layers(&self, animations: &AnimationSet) -> Vec<AnimLayer> {
+        let mut layers = Vec::new();
+        if let Some(anim) = self.animation(animations) {
+            layers.push(AnimLayer {
+                animation: self.animation_name(self.current()),
+                frame: self.frame(anim),
+                alpha: 1.0,
+            });
+        }
+        if let Some(ref fading) = self.fading {
+            layers.push(AnimLayer {
+                animation: fading.animation.clone(),
+                frame: fading.frame,
+                alpha: (fading.left / fading.blend) as f32,
+            });
+        }
+        layers
+    }
+
+    pub fn draw(&self,
+                animations: &AnimationSet,
+                angle: fphys,
+                draw_state: &DrawState,
+                transform: Matrix2d,
+                gl: &mut GlGraphics) {
+        draw_layers(animations, &self.layers(animations), angle, 1.0, draw_state, transform, gl);
+    }
+
+    //  As draw, queueing into batch
+    pub fn queue(&self,
+                 animations: &AnimationSet,
+                 angle: fphys,
+                 transform: Matrix2d,
+                 batch: &mut SpriteBatch) {
+        for layer in self.layers(animations) {
+            if let Some(anim) = animations.get(&layer.animation) {
+                let color = [1.0, 1.0, 1.0, layer.alpha];
+                anim.queue_frame(layer.frame, angle, color, transform, batch);
+            }
+        }
+    }
+}
+
+pub fn draw_layers(animations: &AnimationSet,
+                   layers: &[AnimLayer],
+                   angle: fphys,
+                   alpha: f32,
+                   draw_state: &DrawState,
+                   transform: Matrix2d,
+                   gl: &mut GlGraphics) {
+    for layer in layers {
+        if let Some(anim) = animations.get(&layer.animation) {
+            let color = [1.0, 1.0, 1.0, layer.alpha * alpha];
+            anim.draw_frame(layer.frame, angle, color, draw_state, transform, gl);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use image;
+use opengl_graphics::{CreateTexture, Format, Texture};
+use sprite_sheet::{full_rect, texture_settings};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/*
+ * Every png under sprites/ is packed into a few large atlas pages the first
+ * time a sprite is asked for, so tiles and animation frames share textures
+ * and the sprite batch can draw runs of them at once.
+ *
+ * Pngs bigger than MAX_PACKED in either direction, and sheet images with a
+ * json next to them, which are atlases already, are loaded on their own.
+ * So are pngs changed since packing, so reloading a descriptor still picks
+ * up edited frames.
+ */
+
+pub const ATLAS_DIR: &'static str = "sprites";
+const PAGE_SIZE: u32 = 2048;
+const MAX_PACKED: u32 = 512;
+//  Transparent pixels between sprites so neighbours never bleed in
+const PADDING: u32 = 1;
+
+//  A texture and the part of it holding one sprite
+#[derive(Clone)]
+pub struct AtlasSprite {
+    pub texture: Rc<Texture>,
+    pub src: [f64; 4],
+}
+
+struct Packed {
+    page: usize,
+    src: [f64; 4],
+    modified: Option<SystemTime>,
+}
+
+pub struct Atlas {
+    pages: Vec<Rc<Texture>>,
+    sprites: HashMap<PathBuf, Packed>,
+}
+
+//  Drops "./" so "sprites/a.png" and "./sprites/a.png" are the same sprite
+fn key(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn find_pngs(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("could not read {:?}: {}", dir, e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            find_pngs(&path, found)?;
+        } else if path.extension().map_or(false, |e| e == "png") &&
+                  !path.with_extension("json").exists() {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+//  A page being filled a shelf at a time, left to right
+struct PageBuilder {
+    pixels: Vec<u8>,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_h: u32,
+}
+
+impl PageBuilder {
+    fn new() -> Self {
+        PageBuilder {
+            pixels: Vec::new(),
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_h: 0,
+        }
+    }
+
+    //  Top left for a w by h sprite, None once the page is full
+    fn place(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if self.shelf_x + w > PAGE_SIZE {
+            self.shelf_x = 0;
+            self.shelf_y += self.shelf_h + PADDING;
+            self.shelf_h = 0;
+        }
+        if self.shelf_y + h > PAGE_SIZE {
+            return None;
+        }
+        let pos = (self.shelf_x, self.shelf_y);
+        self.shelf_x += w + PADDING;
+        self.shelf_h = self.shelf_h.max(h);
+        let rows = (self.shelf_y + self.shelf_h) as usize;
+        self.pixels.resize(rows * PAGE_SIZE as usize * 4, 0);
+        Some(pos)
+    }
+
+    fn blit(&mut self, x: u32, y: u32, w: u32, rgba: &[u8]) {
+        let row_len = w as usize * 4;
+        for (row, line) in rgba.chunks(row_len).enumerate() {
+            let start = ((y as usize + row) * PAGE_SIZE as usize + x as usize) * 4;
+            self.pixels[start..start + row_len].copy_from_slice(line);
+        }
+    }
+
+    fn upload(&self) -> Result<Texture, String> {
+        let h = self.pixels.len() as u32 / (PAGE_SIZE * 4);
+        Texture::create(&mut (), Format::Rgba8, &self.pixels, [PAGE_SIZE, h], &texture_settings())
+    }
+}
+
+impl Atlas {
+    pub fn pack(dir: &Path) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        find_pngs(dir, &mut paths)?;
+
+        let mut images = Vec::new();
+        for path in paths {
+            let rgba = image::open(&path)
+                .map_err(|e| format!("could not load {:?}: {}", path, e))?
+                .to_rgba();
+            let (w, h) = rgba.dimensions();
+            if w <= MAX_PACKED && h <= MAX_PACKED {
+                images.push((path, w, h, rgba.into_raw()));
+            }
+        }
+        //  Tallest first keeps shelves full, names keep packing stable
+        images.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+
+        let mut pages = Vec::new();
+        let mut sprites = HashMap::new();
+        let mut builder = PageBuilder::new();
+        for (path, w, h, rgba) in images {
+            let (x, y) = match builder.place(w, h) {
+                Some(pos) => pos,
+                None => {
+                    pages.push(Rc::new(builder.upload()?));
+                    builder = PageBuilder::new();
+                    builder.place(w, h).unwrap()
+                }
+            };
+            builder.blit(x, y, w, &rgba);
+            sprites.insert(key(&path),
+                           Packed {
+                               page: pages.len(),
+                               src: [x as f64, y as f64, w as f64, h as f64],
+                               modified: modified(&path),
+                           });
+        }
+        if !builder.pixels.is_empty() {
+            pages.push(Rc::new(builder.upload()?));
+        }
+        Ok(Atlas {
+            pages: pages,
+            sprites: sprites,
+        })
+    }
+
+    //  Sprites packed, for --draw-stats
+    pub fn sprite_count(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<AtlasSprite> {
+        let packed = self.sprites.get(&key(path))?;
+        if packed.modified.is_some() && modified(path) != packed.modified {
+            return None;
+        }
+        Some(AtlasSprite {
+            texture: self.pages[packed.page].clone(),
+            src: packed.src,
+        })
+    }
+}
+
+//  Textures belong to the thread with the GL context, so the atlas does too
+thread_local! {
+    static ATLAS: RefCell<Option<Rc<Atlas>>> = RefCell::new(None);
+}
+
+//  The atlas of ATLAS_DIR, packed on first use
+pub fn atlas() -> Result<Rc<Atlas>, String> {
+    ATLAS.with(|a| {
+        if let Some(ref atlas) = *a.borrow() {
+            return Ok(atlas.clone());
+        }
+        let atlas = Rc::new(Atlas::pack(Path::new(ATLAS_DIR))?);
+        *a.borrow_mut() = Some(atlas.clone());
+        Ok(atlas)
+    })
+}
+
+//  The sprite at path from the atlas, or in a texture of its own if it
+//  wasn't packed
+pub fn load_sprite<P: AsRef<Path>>(path: P) -> Result<AtlasSprite, String> {
+    let path = path.as_ref();
+    if let Some(sprite) = atlas()?.get(path) {
+        return Ok(sprite);
+    }
+    let texture = Texture::from_path_settings(path, &texture_settings())
+        .map_err(|e| format!("could not load {:?}: {}", path, e))?;
+    Ok(AtlasSprite {
+        src: full_rect(&texture),
+        texture: Rc::new(texture),
+    })
+}
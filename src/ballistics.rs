@@ -0,0 +1,136 @@
+/*
+ * Aiming for projectiles affected by gravity
+ *
+ * Positions are in world space, with y increasing downwards.
+ * Speeds and gravity are in the units PhysDyn integrates with.
+ */
+
+use collision::{BBOwnerType, BoundingBox};
+use game::{Height, Pos, Vel, Width, fphys};
+use world::World;
+
+use std::f64::consts::{FRAC_PI_4, PI};
+
+//  Number of points sampled along an arc when checking for walls
+const ARC_SAMPLES: u32 = 24;
+
+//  Size of the box swept along an arc
+const ARC_PROBE: fphys = 4.0;
+
+//  Solve for the launch angles that hit (dx, dy) from the origin
+//
+//  Returns (low, high) angles measured from the positive x axis, with
+//  negative angles pointing upwards, or None if the target is out of range
+pub fn solve_angles(speed: fphys,
+                    gravity: fphys,
+                    dx: fphys,
+                    dy: fphys)
+                    -> Option<(fphys, fphys)> {
+    if gravity <= 0.0 {
+        let angle = dy.atan2(dx);
+        return Some((angle, angle));
+    }
+
+    //  Work with height above the origin so the usual formula applies
+    let x = dx.abs();
+    let h = -dy;
+    let v2 = speed * speed;
+    let discriminant = v2 * v2 - gravity * (gravity * x * x + 2.0 * h * v2);
+    if discriminant < 0.0 || x == 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let low = ((v2 - root) / (gravity * x)).atan();
+    let high = ((v2 + root) / (gravity * x)).atan();
+
+    Some((to_world_angle(low, dx), to_world_angle(high, dx)))
+}
+
+//  Convert an elevation above the horizontal into a world space angle
+fn to_world_angle(elevation: fphys, dx: fphys) -> fphys {
+    if dx < 0.0 {
+        -(PI - elevation)
+    } else {
+        -elevation
+    }
+}
+
+pub fn vel_from_angle(speed: fphys, angle: fphys) -> Vel {
+    Vel(speed * angle.cos(), speed * angle.sin())
+}
+
+//  Walk along the arc from start to target checking for blocks in the way
+pub fn arc_clear(start: Pos,
+                 vel: Vel,
+                 gravity: fphys,
+                 target: Pos,
+                 world: &World)
+                 -> bool {
+    let Pos(sx, sy) = start;
+    let Vel(vx, vy) = vel;
+    let Pos(tx, _) = target;
+
+    //  Time taken to reach the target's x position
+    let t_end = if vx.abs() > 0.0 {
+        ((tx - sx) / vx).abs()
+    } else {
+        return true;
+    };
+
+    for i in 1..ARC_SAMPLES {
+        let t = t_end * (i as fphys) / (ARC_SAMPLES as fphys);
+        let x = sx + vx * t;
+        let y = sy + vy * t + 0.5 * gravity * t * t;
+        let probe = BoundingBox::new(Pos(x - ARC_PROBE / 2.0, y - ARC_PROBE / 2.0),
+                                     Width(ARC_PROBE),
+                                     Height(ARC_PROBE));
+        let blocked = world.buffer().iter().any(|&(ref p, ref bb)| {
+            p.owner_type.contains(BBOwnerType::BLOCK) && probe.check_col(bb)
+        });
+        if blocked {
+            return false;
+        }
+    }
+    true
+}
+
+//  Choose a launch velocity to hit target from start
+//
+//  Prefers the low arc, falling back to the high arc when a wall is in the
+//  way. Targets out of range are fired at 45 degrees to get as close as
+//  possible.
+pub fn aim(start: Pos,
+           target: Pos,
+           speed: fphys,
+           gravity: fphys,
+           world: &World)
+           -> Vel {
+    let Pos(sx, sy) = start;
+    let Pos(tx, ty) = target;
+    let dx = tx - sx;
+    let dy = ty - sy;
+
+    match solve_angles(speed, gravity, dx, dy) {
+        Some((low, high)) => {
+            let v_low = vel_from_angle(speed, low);
+            if arc_clear(start, v_low, gravity, target, world) {
+                return v_low;
+            }
+            let v_high = vel_from_angle(speed, high);
+            if arc_clear(start, v_high, gravity, target, world) {
+                v_high
+            } else {
+                v_low
+            }
+        }
+        None => {
+            let angle = if dx < 0.0 {
+                -(PI - FRAC_PI_4)
+            } else {
+                -FRAC_PI_4
+            };
+            vel_from_angle(speed, angle)
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//  Checks every descriptor and world file against its schema without
+//  starting the game, eg. `noise-lint` from the game directory or
+//  `noise-lint path/to/noise`
+//  Exits with 1 if any file has a problem
+
+extern crate rustc_serialize;
+
+#[path = "../schema.rs"]
+#[allow(dead_code)]
+mod schema;
+
+use schema::{Schema, load_resolved, read_json, validate};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+//  Every json file under dir, sorted so the output is stable
+fn json_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.is_dir() {
+                files.extend(json_files(&path));
+            } else if path.extension().map_or(false, |e| e == "json") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn descriptor_schema(path: &Path) -> Option<&'static Schema> {
+    let dir = path.parent().and_then(|p| p.file_name()).and_then(|d| d.to_str());
+    match dir {
+        Some("enemy") => return Some(&schema::ENEMY),
+        Some("boss") => return Some(&schema::BOSS),
+        Some("projectile") => return Some(&schema::PROJECTILE),
+        Some("anim") => return Some(&schema::ANIMATED),
+        _ => {}
+    }
+    match path.file_name().and_then(|f| f.to_str()) {
+        Some("world.json") => Some(&schema::WORLD_CONSTANTS),
+        Some("player.json") => Some(&schema::PLAYER),
+        Some("grapple.json") => Some(&schema::GRAPPLE),
+        _ => None,
+    }
+}
+
+//  Files other descriptors inherit from, these are only checked as part
+//  of the files extending them
+fn extended_files(descriptors: &[PathBuf]) -> HashSet<PathBuf> {
+    descriptors.iter()
+        .filter_map(|path| read_json(path).ok())
+        .filter_map(|obj| {
+            obj.get("extends")
+                .and_then(|e| e.as_string())
+                .map(PathBuf::from)
+        })
+        .collect()
+}
+
+//  Prints the problems with one file, returning how many there were
+fn lint(path: &Path, schema: &Schema, resolve_extends: bool) -> usize {
+    let loaded = if resolve_extends {
+        load_resolved(path)
+    } else {
+        read_json(path)
+    };
+    let errors = match loaded {
+        Ok(obj) => {
+            validate(schema, &obj)
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        }
+        Err(e) => vec![e],
+    };
+    for e in &errors {
+        println!("{}: {}", path.display(), e);
+    }
+    errors.len()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Some(root) = args.get(1) {
+        if let Err(e) = env::set_current_dir(root) {
+            println!("Could not open {}: {}", root, e);
+            process::exit(1);
+        }
+    }
+
+    let descriptors = json_files(Path::new("descriptors"));
+    let worlds = json_files(Path::new("worlds"));
+    let extended = extended_files(&descriptors);
+
+    let mut checked = 0;
+    let mut errors = 0;
+    for path in &descriptors {
+        match descriptor_schema(path) {
+            Some(schema) => {
+                errors += lint(path, schema, true);
+                checked += 1;
+            }
+            None if extended.contains(path) => {}
+            None => println!("{}: no schema, not checked", path.display()),
+        }
+    }
+    for path in &worlds {
+        errors += lint(path, &schema::WORLD_FILE, false);
+        checked += 1;
+    }
+
+    println!("{} files checked, {} problems", checked, errors);
+    process::exit(if errors == 0 { 0 } else { 1 });
+}
@@ -0,0 +1,279 @@
+/*
+ * Boss encounters
+ *
+ * A boss waits until it is triggered, then locks the player into an arena
+ * and fights through a number of phases picked by its remaining hp
+ */
+
+use animator::Animator;
+use block::create_clip;
+use collision::*;
+use descriptors::{BossDescriptor, HumanoidDescriptor, WorldDescriptor};
+use draw::{CameraPriority, Rectangle, ViewArena};
+use enemy_graphics::*;
+use game::*;
+use humanoid::*;
+use logic::*;
+use overlay::BossStatus;
+use physics::{PhysDyn, Physical};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tools::arc_mut;
+use weapons::Wieldable;
+
+pub enum BossBehaviour {
+    //  Run at the player
+    Chase,
+    //  Stay at the current weapon's preferred range
+    KeepDistance,
+    //  Hold position and attack
+    Stationary,
+}
+
+//  Thickness of the walls used to close off the arena
+const ARENA_WALL: fphys = 64.0;
+const ARENA_CAMERA_PRIORITY: CameraPriority = CameraPriority(10);
+const ARENA_CAMERA_WEIGHT: fphys = 30.0;
+
+struct BossLogic {
+    descr: Rc<BossDescriptor>,
+    world_descr: Rc<WorldDescriptor>,
+    physics: Arc<Mutex<PhysDyn>>,
+    draw: Arc<Mutex<EnemyGphx>>,
+    arena: Rectangle,
+    active: bool,
+    arena_clips: Vec<Id>,
+    phase: usize,
+    weapons: Vec<Box<Wieldable>>,
+    attack: usize,
+    attack_cd: fphys,
+    cds: Cooldowns,
+    hp: fphys,
+}
+
+impl BossLogic {
+    //  Index of the phase for the current hp
+    fn phase_for_hp(&self) -> usize {
+        let frac = self.hp / self.descr.hp;
+        let mut phase = 0;
+        for (i, p) in self.descr.phases.iter().enumerate() {
+            if frac <= p.hp_fraction {
+                phase = i;
+            }
+        }
+        phase
+    }
+
+    fn enter_phase(&mut self, phase: usize) {
+        self.phase = phase;
+        self.weapons = self.descr.phases[phase]
+            .attacks
+            .iter()
+            .map(|a| a.weapon.to_wieldable())
+            .collect::<Vec<Box<Wieldable>>>();
+        self.attack = 0;
+        self.attack_cd = 0.0;
+    }
+
+    fn start(&mut self, args: &LogicUpdateArgs) {
+        self.active = true;
+
+        //  Wall off the sides and top of the arena
+        let Rectangle { x, y, w, h } = self.arena;
+        let walls = [(Pos(x - ARENA_WALL, y), Width(ARENA_WALL), Height(h)),
+                     (Pos(x + w, y), Width(ARENA_WALL), Height(h)),
+                     (Pos(x - ARENA_WALL, y - ARENA_WALL),
+                      Width(w + 2.0 * ARENA_WALL),
+                      Height(ARENA_WALL))];
+        for &(pos, ww, wh) in walls.iter() {
+            let clip_id = args.world.generate_id();
+            let clip = create_clip(clip_id, pos, ww, wh, args.world);
+            args.metabuffer.issue(MetaCommand::CreateObject(clip));
+            self.arena_clips.push(clip_id);
+        }
+
+        let camera = ViewArena::new(Rectangle::new(x, y, w, h),
+                                    ARENA_CAMERA_WEIGHT,
+                                    ARENA_CAMERA_PRIORITY);
+        args.metabuffer.issue(MetaCommand::AddCamera(args.id, Box::new(camera)));
+        args.metabuffer.issue(MetaCommand::Dialogue(9, self.descr.name.clone()));
+    }
+
+    fn defeated(&mut self, args: &LogicUpdateArgs) {
+        for clip_id in self.arena_clips.drain(..) {
+            args.metabuffer.issue(MetaCommand::RemoveObject(clip_id));
+        }
+        args.metabuffer.issue(MetaCommand::RemoveCamera(args.id));
+        args.metabuffer.issue(MetaCommand::BossStatus(None));
+        args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
+    }
+}
+
+impl Logical for BossLogic {
+    fn tick(&mut self, args: &LogicUpdateArgs) {
+        let phys_info = get_phys_info(self.physics.clone());
+        let Pos(x, y) = phys_info.pos;
+        let Vel(xvel, yvel) = phys_info.vel;
+        let dt = args.piston.dt as fphys;
+
+        for m in args.message_buffer.read_buffer() {
+            match m {
+                ObjMessage::MTrigger => {
+                    if !self.active {
+                        self.start(args);
+                    }
+                }
+                ObjMessage::MDamage(dmg) => {
+                    if self.active {
+                        self.hp -= dmg;
+                    }
+                }
+                ObjMessage::MDescriptorsReloaded(reloaded) => {
+                    if let Some(ref world_descr) = reloaded.world {
+                        self.world_descr = world_descr.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !self.active {
+            return;
+        }
+
+        if self.hp <= 0.0 {
+            self.defeated(args);
+            return;
+        }
+
+        let phase = self.phase_for_hp();
+        if phase != self.phase {
+            self.enter_phase(phase);
+        }
+
+        args.world.report_health(args.id, self.hp, self.descr.hp);
+        args.metabuffer.issue(MetaCommand::BossStatus(Some(BossStatus {
+            name: self.descr.name.clone(),
+            hp: self.hp,
+            hp_max: self.descr.hp,
+        })));
+
+        let target = args.world
+            .get(args.world.player_id())
+            .map(|(_, bb)| bb.pos);
+
+        let mut attacked = false;
+        let move_input = match target {
+            Some(Pos(tx, ty)) => {
+                let centre = Pos(x + phys_info.w.0 / 2.0, y + phys_info.h.0 / 2.0);
+
+                //  Attack pattern
+                if self.attack_cd <= 0.0 && !self.weapons.is_empty() {
+                    let attack = self.attack % self.weapons.len();
+                    self.weapons[attack].fire(Pos(tx, ty), centre, args);
+                    self.attack_cd = self.descr.phases[self.phase].attacks[attack].cd;
+                    self.attack = attack + 1;
+                    attacked = true;
+                } else {
+                    self.attack_cd -= dt;
+                }
+
+                let xdiff = tx - x;
+                match self.descr.phases[self.phase].behaviour {
+                    BossBehaviour::Chase => {
+                        let mut ret = hi_from_xdir(xdiff.signum());
+                        if (ty - y) < -30.0 {
+                            ret |= HumanoidInput::JUMP;
+                        }
+                        ret
+                    }
+                    BossBehaviour::KeepDistance => {
+                        let desired = self.weapons
+                            .get(self.attack % self.weapons.len().max(1))
+                            .map(|w| w.desired_distance())
+                            .unwrap_or(0.0);
+                        if xdiff.abs() < desired {
+                            hi_from_xdir(-xdiff.signum())
+                        } else {
+                            hi_from_xdir(xdiff.signum())
+                        }
+                    }
+                    BossBehaviour::Stationary => HumanoidInput::NONE,
+                }
+            }
+            None => HumanoidInput::NONE,
+        };
+
+        let on_ground = self.physics.lock().unwrap().on_ground;
+        {
+            let mut d = self.draw.lock().unwrap();
+            if xvel > 1.0 {
+                d.reverse = false;
+            }
+            if xvel < -1.0 {
+                d.reverse = true;
+            }
+            //  Bosses attack on their own timing, the animation only follows
+            d.animator.set_bool("on_ground", on_ground);
+            d.animator.set("speed", xvel.abs());
+            d.animator.set("yvel", yvel);
+            d.animator.set_bool("attacking", attacked);
+            d.animator.update(dt, &self.descr.body.animations);
+        }
+
+        humanoid_input(args,
+                       &move_input,
+                       &mut self.cds,
+                       &self.descr.body.to_move_descr(self.world_descr.clone()),
+                       self.physics.clone());
+    }
+}
+
+pub fn create(id: Id,
+              pos: Pos,
+              arena: Rectangle,
+              descr: Rc<BossDescriptor>,
+              world_descr: Rc<WorldDescriptor>)
+              -> GameObj {
+    let body = descr.body.clone();
+    let graphics = EnemyGphx {
+        pos: pos,
+        scale: body.scale,
+        animator: Animator::new(body.animator.clone()),
+        reverse: false,
+        manager: body.clone(),
+    };
+    let g = arc_mut(graphics);
+    let props = BBProperties::new(id, BBOwnerType::ENEMY);
+    let mut phys = PhysDyn::new(props,
+                                pos,
+                                Mass(1.0),
+                                body.maxspeed,
+                                body.width,
+                                body.height,
+                                true,
+                                g.clone());
+    phys.collide_with = BBOwnerType::BLOCK | BBOwnerType::PLATFORM;
+    let p = arc_mut(phys);
+
+    let name = descr.name.clone();
+    let mut logic = BossLogic {
+        hp: descr.hp,
+        descr: descr,
+        world_descr: world_descr,
+        physics: p.clone(),
+        draw: g.clone(),
+        arena: arena,
+        active: false,
+        arena_clips: Vec::new(),
+        phase: 0,
+        weapons: Vec::new(),
+        attack: 0,
+        attack_cd: 0.0,
+        cds: Cooldowns::new(),
+    };
+    logic.enter_phase(0);
+    let l = arc_mut(logic);
+
+    GameObj::new(id, name, g, p, l)
+}
@@ -1,26 +1,50 @@
+use boss::BossBehaviour;
 use game::*;
 use humanoid::*;
-use opengl_graphics::{Filter, Texture};
-use piston_window::TextureSettings;
-use rustc_serialize::json::Json;
+use rustc_serialize::json::{Array, Json};
 use rustc_serialize::json::Object;
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Error, ErrorKind};
 use std::io::Read;
 use std::path::Path;
 
 use std::rc::Rc;
+use animator::{AnimationSet, AnimatorDescriptor};
+use atlas::{AtlasSprite, load_sprite};
+use schema::{self, Schema, load_resolved, merge_fields, validate};
+use sprite_sheet::{Animation, SheetCache, SpriteSheet, cached_sheet};
 use weapons::*;
 
 /*
  * Descriptors are used to specify constants about the game in simple json files
  * see ./descriptors/player.json etc
+ *
+ * A descriptor can inherit from another file with "extends", giving only the
+ * fields it changes, eg. "extends":"descriptors/enemy_base.json"
+ *
+ * Animations are numbered pngs, eg. "idle_frames":3 and "idle_path":"a/idle"
+ * for a/idle1.png to a/idle3.png, or a tag of a sprite sheet exported from
+ * Aseprite, eg. "idle_sheet":"sprites/player/player.json","idle_tag":"idle"
+ *
+ * Which animation is shown is decided by the "animator" object, see animator.rs
  */
 pub trait Descriptor {
     fn new(&Path) -> Result<Rc<Self>, Error>;
 }
 
+//  Descriptors re-read from disk while the game is running
+//  Sent to every object so each can swap in the ones it uses
+#[derive(Default)]
+pub struct ReloadedDescriptors {
+    pub world: Option<Rc<WorldDescriptor>>,
+    pub player: Option<Rc<PlayerDescriptor>>,
+    pub grapple: Option<Rc<GrappleDescriptor>>,
+    //  Keyed by enemy name
+    pub enemies: HashMap<String, Rc<EnemyDescriptor>>,
+}
+
 pub trait HumanoidDescriptor : Descriptor {
     fn to_move_descr(&self, world_descr: Rc<WorldDescriptor>) -> MovementDescriptor;
 }
@@ -31,17 +55,19 @@ pub struct WorldDescriptor {
 }
 
 pub struct PlayerDescriptor {
-    pub idle: Vec<Texture>,
-    pub running: Vec<Texture>,
-    pub falling: Vec<Texture>,
-    pub jumping: Vec<Texture>,
-    pub swinging: Vec<Texture>,
-    pub dashing: Vec<Texture>,
+    //  idle, running, falling, jumping, swinging and dashing, with
+    //  aiming<i> and firing<i> for each weapon
+    pub animations: AnimationSet,
+    pub animator: Rc<AnimatorDescriptor>,
     pub speed: fphys,
     pub scale: fphys,
     pub width: Width,
     pub height: Height,
 
+    pub weapons: Vec<WeaponDescriptor>,
+    //  Time the firing animation is shown for after releasing a weapon
+    pub fire_anim_time: fphys,
+
     pub start_hp: fphys,
     pub friction: fphys,
     pub friction_air_mult: fphys,
@@ -86,6 +112,32 @@ pub fn get_float(dname: &str, obj: &Object, field: &str) -> Result<f64, Error> {
                             format!("'{}' is not a number", field).as_str()))
 }
 
+//  As get_float, but a missing field takes the default
+pub fn get_float_or(dname: &str,
+                    obj: &Object,
+                    field: &str,
+                    default: f64)
+                    -> Result<f64, Error> {
+    if obj.contains_key(field) {
+        get_float(dname, obj, field)
+    } else {
+        Ok(default)
+    }
+}
+
+//  As get_number, but a missing field takes the default
+pub fn get_number_or(dname: &str,
+                     obj: &Object,
+                     field: &str,
+                     default: u64)
+                     -> Result<u64, Error> {
+    if obj.contains_key(field) {
+        get_number(dname, obj, field)
+    } else {
+        Ok(default)
+    }
+}
+
 pub fn get_string(dname: &str,
                   obj: &Object,
                   field: &str)
@@ -98,12 +150,21 @@ pub fn get_string(dname: &str,
                             format!("'{}' is not a string", field).as_str()))?))
 }
 
-// Load texture
-pub fn load_from(ts: &TextureSettings,
-                 dname: &str,
-                 count: usize,
-                 path: &str)
-                 -> Result<Vec<Texture>, Error> {
+pub fn get_array(dname: &str, obj: &Object, field: &str) -> Result<Array, Error> {
+    let raw = obj.get(field)
+        .ok_or(error_simple(dname,
+                            format!("has no field '{}'", field).as_str()))?;
+    let a = raw.as_array()
+        .ok_or(error_simple(dname,
+                            format!("'{}' is not an array", field).as_str()))?;
+    Ok(a.clone())
+}
+
+// Load numbered sprites, from the atlas where they were packed
+pub fn load_sprites(dname: &str,
+                    count: usize,
+                    path: &str)
+                    -> Result<Vec<AtlasSprite>, Error> {
     let mut r = Vec::new();
     for i in 1..count + 1 {
         let path_i = format!("{}{}.png", path, i);
@@ -111,13 +172,75 @@ pub fn load_from(ts: &TextureSettings,
                                format!("could not load file {}",
                                        path_i.as_str())
                                    .as_str());
-        let t: Texture =
-            Texture::from_path_settings(path_i, ts).map_err(|_| err)?;
-        r.push(t);
+        r.push(load_sprite(&path_i).map_err(|_| err)?);
     }
     Ok(r)
 }
 
+//  An animation given as <name>_sheet and optionally <name>_tag, or as
+//  <name>_frames numbered pngs from <name>_path each shown for 1 / speed seconds
+pub fn get_animation(dname: &str,
+                     obj: &Object,
+                     name: &str,
+                     speed: fphys,
+                     sheets: &mut SheetCache)
+                     -> Result<Animation, Error> {
+    get_animation_fields(dname, obj, &format!("{}_", name), speed, sheets)
+}
+
+//  As get_animation, for fields named <prefix>sheet, <prefix>path and so on
+fn get_animation_fields(dname: &str,
+                        obj: &Object,
+                        prefix: &str,
+                        speed: fphys,
+                        sheets: &mut SheetCache)
+                        -> Result<Animation, Error> {
+    let sheet_field = format!("{}sheet", prefix);
+    if obj.contains_key(&sheet_field) {
+        let sheet_path = get_string(dname, obj, &sheet_field)?;
+        let tag = obj.get(&format!("{}tag", prefix)).and_then(|t| t.as_string());
+        let sheet = cached_sheet(sheets, &sheet_path)
+            .map_err(|e| error_simple(dname, &e))?;
+        return SpriteSheet::animation(&sheet, tag)
+            .map_err(|e| error_simple(dname, &format!("{} {}", sheet_path, e)));
+    }
+    let frames = get_number_or(dname, obj, &format!("{}frames", prefix), 1)?;
+    let path = get_string(dname, obj, &format!("{}path", prefix))?;
+    let sprites = load_sprites(dname, frames as usize, &path)?;
+    let duration = if speed > 0.0 { 1.0 / speed } else { 1.0 };
+    let sheet = Rc::new(SpriteSheet::from_sprites(sprites, duration));
+    let all_frames = (0..sheet.frames.len()).collect();
+    Ok(Animation::new(sheet, all_frames))
+}
+
+//  Each of names loaded with get_animation
+pub fn get_animations(dname: &str,
+                      obj: &Object,
+                      names: &[&str],
+                      speed: fphys,
+                      sheets: &mut SheetCache)
+                      -> Result<AnimationSet, Error> {
+    let mut animations = AnimationSet::new();
+    for name in names {
+        animations.insert((*name).to_owned(),
+                          get_animation(dname, obj, name, speed, sheets)?);
+    }
+    Ok(animations)
+}
+
+//  The "animator" object, checked against the animations it can show
+pub fn get_animator(dname: &str,
+                    obj: &Object,
+                    animations: &AnimationSet)
+                    -> Result<Rc<AnimatorDescriptor>, Error> {
+    let animator_obj = obj.get("animator")
+        .and_then(|a| a.as_object())
+        .ok_or(error_simple(dname, "has no 'animator' object"))?;
+    let animator = AnimatorDescriptor::from_json(dname, animator_obj)?;
+    animator.check_animations(dname, animations)?;
+    Ok(animator)
+}
+
 // Load json object
 pub fn load_json(dname: &str, json_path: &Path) -> Result<Object, Error> {
     let mut f = (File::open(json_path)).map_err(|_| {
@@ -135,12 +258,32 @@ pub fn load_json(dname: &str, json_path: &Path) -> Result<Object, Error> {
     Ok(o.clone())
 }
 
+//  Load a descriptor's json, following "extends", and check it against schema
+pub fn load_descriptor_json(dname: &str,
+                            schema: &Schema,
+                            json_path: &Path)
+                            -> Result<Object, Error> {
+    let obj = load_resolved(json_path).map_err(|e| error_simple(dname, &e))?;
+    check_schema(dname, schema, &obj)?;
+    Ok(obj)
+}
+
+//  Fails with every problem found in obj, one per line
+pub fn check_schema(dname: &str, schema: &Schema, obj: &Object) -> Result<(), Error> {
+    let errors = validate(schema, obj);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let lines = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    Err(error_simple(dname, &format!("\n  {}", lines.join("\n  "))))
+}
+
 impl Descriptor for WorldDescriptor {
     fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
-        let obj = load_json("world", json_path)?;
+        let obj = load_descriptor_json("world", &schema::WORLD_CONSTANTS, json_path)?;
 
         let gravity_base = get_float("world", &obj, "gravity")?;
-        let gravity_down_mult = get_float("world", &obj, "gravity_down_mult")?;
+        let gravity_down_mult = get_float_or("world", &obj, "gravity_down_mult", 1.0)?;
 
         Ok(Rc::new(WorldDescriptor {
           gravity_up: gravity_base,
@@ -151,80 +294,63 @@ impl Descriptor for WorldDescriptor {
 
 impl Descriptor for PlayerDescriptor {
     fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
-        let obj = load_json("player", json_path)?;
-
-        let idle_frames = get_number("player", &obj, "idle_frames")?;
-        let running_frames = get_number("player", &obj, "running_frames")?;
-        let jumping_frames = get_number("player", &obj, "jumping_frames")?;
-        let falling_frames = get_number("player", &obj, "falling_frames")?;
-        let swinging_frames = get_number("player", &obj, "swinging_frames")?;
-        let dashing_frames = get_number("player", &obj, "dashing_frames")?;
-
-        let idle_path = get_string("player", &obj, "idle_path")?;
-        let running_path = get_string("player", &obj, "running_path")?;
-        let falling_path = get_string("player", &obj, "falling_path")?;
-        let jumping_path = get_string("player", &obj, "jumping_path")?;
-        let swinging_path = get_string("player", &obj, "swinging_path")?;
-        let dashing_path = get_string("player", &obj, "dashing_path")?;
+        let obj = load_descriptor_json("player", &schema::PLAYER, json_path)?;
 
         let speed = get_float("player", &obj, "speed")?;
-        let scale = get_float("player", &obj, "scale")?;
+        let scale = get_float_or("player", &obj, "scale", 1.0)?;
         let width = Width(get_float("player", &obj, "width")?);
         let height = Height(get_float("player", &obj, "height")?);
 
-        let mut ts = TextureSettings::new();
-        ts.set_mag(Filter::Nearest);
-
-        let idle =
-            load_from(&ts, "player", idle_frames as usize, idle_path.as_str())?;
-        let running = load_from(&ts,
-                                "player",
-                                running_frames as usize,
-                                running_path.as_str())?;
-        let falling = load_from(&ts,
-                                "player",
-                                falling_frames as usize,
-                                falling_path.as_str())?;
-        let jumping = load_from(&ts,
-                                "player",
-                                jumping_frames as usize,
-                                jumping_path.as_str())?;
-        let swinging = load_from(&ts,
-                                 "player",
-                                 swinging_frames as usize,
-                                 swinging_path.as_str())?;
-        let dashing = load_from(&ts,
-                                "player",
-                                dashing_frames as usize,
-                                dashing_path.as_str())?;
+        let mut sheets = SheetCache::new();
+        let mut animations = get_animations("player",
+                                            &obj,
+                                            &["idle", "running", "falling", "jumping",
+                                              "swinging", "dashing"],
+                                            speed,
+                                            &mut sheets)?;
+
+        let mut weapons = Vec::new();
+        for (i, poss_weapon) in get_array("player", &obj, "weapons")?
+            .iter()
+            .enumerate() {
+            let weapon_obj = poss_weapon.as_object()
+                .ok_or(error_simple("player",
+                                    format!("weapon {} not well formed", i)
+                                        .as_str()))?;
+            weapons.push(WeaponDescriptor::from_json(weapon_obj)?);
+            for name in &["aiming", "firing"] {
+                animations.insert(format!("{}{}", name, i),
+                                  get_animation("weapon", weapon_obj, name, speed, &mut sheets)?);
+            }
+        }
+        let animator = get_animator("player", &obj, &animations)?;
 
         Ok(Rc::new(PlayerDescriptor {
             speed: speed,
             scale: scale,
             width: width,
             height: height,
-            idle: idle,
-            running: running,
-            falling: falling,
-            jumping: jumping,
-            swinging: swinging,
-            dashing: dashing,
+            animations: animations,
+            animator: animator,
+            weapons: weapons,
+            fire_anim_time: get_float_or("player", &obj, "fire_anim_time", 0.2)?,
             start_hp: get_float("player", &obj, "start_hp")?,
             friction: get_float("player", &obj, "friction")?,
-            friction_air_mult: get_float("player", &obj, "friction_air_mult")?,
+            friction_air_mult: get_float_or("player", &obj, "friction_air_mult", 1.0)?,
             moveforce: get_float("player", &obj, "moveforce")?,
-            moveforce_air_mult: get_float("player",
-                                          &obj,
-                                          "moveforce_air_mult")?,
+            moveforce_air_mult: get_float_or("player",
+                                             &obj,
+                                             "moveforce_air_mult",
+                                             1.0)?,
             jumpforce: get_float("player", &obj, "jumpforce")?,
             max_runspeed: get_float("player", &obj, "max_runspeed")?,
             maxspeed: get_float("player", &obj, "maxspeed")?,
-            dash_cd: get_float("player", &obj, "dash_cd")?,
-            dash_duration: get_float("player", &obj, "dash_duration")?,
-            dash_invuln: get_float("player", &obj, "dash_invuln")?,
-            dash_force: get_float("player", &obj, "dash_force")?,
-            jump_cd: get_float("player", &obj, "jump_cd")?,
-            damage_cd: get_float("player", &obj, "damage_cd")?,
+            dash_cd: get_float_or("player", &obj, "dash_cd", 0.5)?,
+            dash_duration: get_float_or("player", &obj, "dash_duration", 0.1)?,
+            dash_invuln: get_float_or("player", &obj, "dash_invuln", 0.0)?,
+            dash_force: get_float_or("player", &obj, "dash_force", 0.0)?,
+            jump_cd: get_float_or("player", &obj, "jump_cd", 0.5)?,
+            damage_cd: get_float_or("player", &obj, "damage_cd", 0.4)?,
         }))
     }
 }
@@ -260,7 +386,7 @@ pub struct GrappleDescriptor {
 
 impl Descriptor for GrappleDescriptor {
     fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
-        let obj = load_json("grapple", json_path)?;
+        let obj = load_descriptor_json("grapple", &schema::GRAPPLE, json_path)?;
         Ok(Rc::new(GrappleDescriptor {
             extend_speed: get_float("grapple", &obj, "extend_speed")?,
             retract_speed: get_float("grapple", &obj, "retract_speed")?,
@@ -273,13 +399,100 @@ impl Descriptor for GrappleDescriptor {
     }
 }
 
+pub struct ProjectileDescriptor {
+    pub name: String,
+
+    //  Empty if the projectile should be drawn as a plain rectangle
+    pub sprite: Vec<AtlasSprite>,
+    pub sprite_speed: fphys,
+    pub scale: fphys,
+    pub width: Width,
+    pub height: Height,
+
+    pub speed: fphys,
+    pub gravity_scale: fphys,
+    pub damage: fphys,
+    //  Number of fighters the projectile passes through before it is removed
+    pub pierce: u32,
+    pub lifetime: fphys,
+}
+
+impl Descriptor for ProjectileDescriptor {
+    fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
+        let obj = load_descriptor_json("projectile", &schema::PROJECTILE, json_path)?;
+
+        //  Sprites are optional for projectiles
+        let sprite = if obj.contains_key("sprite_path") {
+            let sprite_frames = get_number_or("projectile", &obj, "sprite_frames", 1)?;
+            let sprite_path = get_string("projectile", &obj, "sprite_path")?;
+            load_sprites("projectile",
+                         sprite_frames as usize,
+                         sprite_path.as_str())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Rc::new(ProjectileDescriptor {
+            name: get_string("projectile", &obj, "name")?,
+            sprite: sprite,
+            sprite_speed: get_float("projectile", &obj, "sprite_speed")?,
+            scale: get_float("projectile", &obj, "scale")?,
+            width: Width(get_float("projectile", &obj, "width")?),
+            height: Height(get_float("projectile", &obj, "height")?),
+            speed: get_float("projectile", &obj, "speed")?,
+            gravity_scale: get_float("projectile", &obj, "gravity_scale")?,
+            damage: get_float("projectile", &obj, "damage")?,
+            pierce: get_number("projectile", &obj, "pierce")? as u32,
+            lifetime: get_float("projectile", &obj, "lifetime")?,
+        }))
+    }
+}
+
+pub fn get_weapon(dname: &str, obj: &Object) -> Result<Weapon, Error> {
+    let weapon_str = get_string(dname, obj, "weapon")?;
+    match weapon_str.as_str() {
+        "melee" => Ok(Weapon::Melee),
+        "bow" => {
+            let projectile_path = get_string(dname, obj, "projectile")?;
+            let projectile =
+                ProjectileDescriptor::new(Path::new(&projectile_path))?;
+            Ok(Weapon::Bow(projectile))
+        }
+        _ => {
+            Err(error_simple(dname,
+                             format!("Unknown weapon {}", weapon_str)
+                                 .as_str()))
+        }
+    }
+}
+
+//  A weapon the player can equip
+pub struct WeaponDescriptor {
+    pub weapon: Weapon,
+    //  None for weapons with unlimited ammo
+    pub ammo: Option<u32>,
+}
+
+impl WeaponDescriptor {
+    fn from_json(obj: &Object) -> Result<Self, Error> {
+        let ammo = if obj.contains_key("ammo") {
+            Some(get_number("weapon", obj, "ammo")? as u32)
+        } else {
+            None
+        };
+        Ok(WeaponDescriptor {
+            weapon: get_weapon("weapon", obj)?,
+            ammo: ammo,
+        })
+    }
+}
+
 pub struct EnemyDescriptor {
     pub name: String,
 
-    pub idle: Vec<Texture>,
-    pub running: Vec<Texture>,
-    pub jumping: Vec<Texture>,
-    pub attacking: Vec<Texture>,
+    //  idle, running, jumping and attacking
+    pub animations: AnimationSet,
+    pub animator: Rc<AnimatorDescriptor>,
     pub speed: fphys,
     pub scale: fphys,
     pub width: Width,
@@ -307,55 +520,63 @@ pub struct EnemyDescriptor {
     pub alert_dist: fphys,
 
     pub bounce_force: fphys,
+
+    //  The json this was built from with extends resolved, and the per
+    //  instance overrides on top, kept so overrides survive a reload
+    pub base: Object,
+    pub overrides: Object,
 }
 
 impl Descriptor for EnemyDescriptor {
     fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
-        let obj = load_json("enemy", json_path)?;
+        let base = load_resolved(json_path).map_err(|e| error_simple("enemy", &e))?;
+        EnemyDescriptor::from_json(base, Object::new())
+    }
+}
+
+impl EnemyDescriptor {
+    //  A variant of descr with fields replaced, eg. from a world object's
+    //  "fields", or descr itself if there are none
+    pub fn with_overrides(descr: &Rc<Self>, fields: &Object) -> Result<Rc<Self>, Error> {
+        if fields.is_empty() {
+            return Ok(descr.clone());
+        }
+        EnemyDescriptor::from_json(descr.base.clone(),
+                                   merge_fields(&descr.overrides, fields))
+    }
 
-        let idle_frames = get_number("enemy", &obj, "idle_frames")?;
-        let running_frames = get_number("enemy", &obj, "running_frames")?;
-        let jumping_frames = get_number("enemy", &obj, "jumping_frames")?;
-        let attacking_frames = get_number("enemy", &obj, "attacking_frames")?;
+    //  The reloaded version of this descriptor, if there is one, with this
+    //  descriptor's overrides applied again
+    pub fn reloaded(&self, reloaded: &ReloadedDescriptors) -> Option<Rc<Self>> {
+        let base_name = get_string("enemy", &self.base, "name").ok()?;
+        let descr = reloaded.enemies.get(&base_name)?;
+        match EnemyDescriptor::with_overrides(descr, &self.overrides) {
+            Ok(d) => Some(d),
+            Err(e) => {
+                println!("Error reapplying overrides to {}: {}", base_name, e);
+                None
+            }
+        }
+    }
 
-        let idle_path = get_string("enemy", &obj, "idle_path")?;
-        let running_path = get_string("enemy", &obj, "running_path")?;
-        let jumping_path = get_string("enemy", &obj, "jumping_path")?;
-        let attacking_path = get_string("enemy", &obj, "attacking_path")?;
+    fn from_json(base: Object, overrides: Object) -> Result<Rc<Self>, Error> {
+        let obj = merge_fields(&base, &overrides);
+        check_schema("enemy", &schema::ENEMY, &obj)?;
 
         let speed = get_float("enemy", &obj, "speed")?;
-        let scale = get_float("enemy", &obj, "scale")?;
+        let scale = get_float_or("enemy", &obj, "scale", 1.0)?;
         let width = Width(get_float("enemy", &obj, "width")?);
         let height = Height(get_float("enemy", &obj, "height")?);
 
-        let weapon_str = get_string("enemy", &obj, "weapon")?;
-        let weapon = (match weapon_str.as_str() {
-            "melee" => Ok(Weapon::Melee),
-            "bow" => Ok(Weapon::Bow),
-            _ => {
-                Err(error_simple("enemy",
-                                 format!("Unknown weapon {}", weapon_str)
-                                     .as_str()))
-            }
-        })?;
+        let weapon = get_weapon("enemy", &obj)?;
 
-        let mut ts = TextureSettings::new();
-        ts.set_mag(Filter::Nearest);
-
-        let idle =
-            load_from(&ts, "enemy", idle_frames as usize, idle_path.as_str())?;
-        let running = load_from(&ts,
-                                "enemy",
-                                running_frames as usize,
-                                running_path.as_str())?;
-        let jumping = load_from(&ts,
-                                "enemy",
-                                jumping_frames as usize,
-                                jumping_path.as_str())?;
-        let attacking = load_from(&ts,
-                                  "enemy",
-                                  attacking_frames as usize,
-                                  attacking_path.as_str())?;
+        let mut sheets = SheetCache::new();
+        let animations = get_animations("enemy",
+                                        &obj,
+                                        &["idle", "running", "jumping", "attacking"],
+                                        speed,
+                                        &mut sheets)?;
+        let animator = get_animator("enemy", &obj, &animations)?;
 
         Ok(Rc::new(EnemyDescriptor {
             name: get_string("enemy", &obj, "name")?,
@@ -363,28 +584,28 @@ impl Descriptor for EnemyDescriptor {
             scale: scale,
             width: width,
             height: height,
-            idle: idle,
-            running: running,
-            jumping: jumping,
-            attacking: attacking,
+            animations: animations,
+            animator: animator,
             weapon: weapon,
             start_hp: get_float("enemy", &obj, "start_hp")?,
             friction: get_float("enemy", &obj, "friction")?,
-            friction_air_mult: get_float("enemy", &obj, "friction_air_mult")?,
+            friction_air_mult: get_float_or("enemy", &obj, "friction_air_mult", 1.0)?,
             moveforce: get_float("enemy", &obj, "moveforce")?,
-            moveforce_air_mult: get_float("enemy", &obj, "moveforce_air_mult")?,
+            moveforce_air_mult: get_float_or("enemy", &obj, "moveforce_air_mult", 1.0)?,
             jumpforce: get_float("enemy", &obj, "jumpforce")?,
             max_runspeed: get_float("enemy", &obj, "max_runspeed")?,
             maxspeed: get_float("enemy", &obj, "maxspeed")?,
-            jump_cd: get_float("enemy", &obj, "jump_cd")?,
-            damage_cd: get_float("enemy", &obj, "damage_cd")?,
-            idle_move_chance: get_float("enemy", &obj, "idle_move_chance")?,
-            idle_stop_chance: get_float("enemy", &obj, "idle_stop_chance")?,
-            alert_dist: get_float("enemy", &obj, "alert_dist")?,
-            bounce_force: get_float("enemy", &obj, "bounce_force")?,
-            dash_cd: get_float("enemy", &obj, "dash_cd")?,
-            dash_duration: get_float("enemy", &obj, "dash_duration")?,
-            dash_force: get_float("enemy", &obj, "dash_force")?,
+            jump_cd: get_float_or("enemy", &obj, "jump_cd", 0.5)?,
+            damage_cd: get_float_or("enemy", &obj, "damage_cd", 0.4)?,
+            idle_move_chance: get_float_or("enemy", &obj, "idle_move_chance", 0.0)?,
+            idle_stop_chance: get_float_or("enemy", &obj, "idle_stop_chance", 0.0)?,
+            alert_dist: get_float_or("enemy", &obj, "alert_dist", 200.0)?,
+            bounce_force: get_float_or("enemy", &obj, "bounce_force", 0.0)?,
+            dash_cd: get_float_or("enemy", &obj, "dash_cd", 0.5)?,
+            dash_duration: get_float_or("enemy", &obj, "dash_duration", 0.1)?,
+            dash_force: get_float_or("enemy", &obj, "dash_force", 0.0)?,
+            base: base,
+            overrides: overrides,
         }))
     }
 }
@@ -406,3 +627,147 @@ impl HumanoidDescriptor for EnemyDescriptor {
         }
     }
 }
+
+pub struct BossAttack {
+    pub weapon: Weapon,
+    pub cd: fphys,
+}
+
+pub struct BossPhase {
+    //  Phase starts once hp drops to this fraction of the maximum
+    pub hp_fraction: fphys,
+    pub behaviour: BossBehaviour,
+    //  Attacks are used in order, looping back to the start
+    pub attacks: Vec<BossAttack>,
+}
+
+pub struct BossDescriptor {
+    pub name: String,
+    //  Sprites and movement are shared with regular enemies
+    pub body: Rc<EnemyDescriptor>,
+    pub hp: fphys,
+    pub phases: Vec<BossPhase>,
+
+    //  As for EnemyDescriptor, kept so world objects can override fields
+    pub base: Object,
+}
+
+impl BossPhase {
+    fn from_json(obj: &Object) -> Result<Self, Error> {
+        let behaviour_str = get_string("boss", obj, "behaviour")?;
+        let behaviour = match behaviour_str.as_str() {
+            "chase" => BossBehaviour::Chase,
+            "keep_distance" => BossBehaviour::KeepDistance,
+            "stationary" => BossBehaviour::Stationary,
+            _ => {
+                return Err(error_simple("boss",
+                                        format!("Unknown behaviour {}",
+                                                behaviour_str)
+                                            .as_str()));
+            }
+        };
+        let mut attacks = Vec::new();
+        for (i, poss_attack) in get_array("boss", obj, "attacks")?
+            .iter()
+            .enumerate() {
+            let attack_obj = poss_attack.as_object()
+                .ok_or(error_simple("boss",
+                                    format!("attack {} not well formed", i)
+                                        .as_str()))?;
+            attacks.push(BossAttack {
+                weapon: get_weapon("boss", attack_obj)?,
+                cd: get_float("boss", attack_obj, "cd")?,
+            });
+        }
+        Ok(BossPhase {
+            hp_fraction: get_float("boss", obj, "hp_fraction")?,
+            behaviour: behaviour,
+            attacks: attacks,
+        })
+    }
+}
+
+impl Descriptor for BossDescriptor {
+    fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
+        let base = load_resolved(json_path).map_err(|e| error_simple("boss", &e))?;
+        BossDescriptor::from_json(base)
+    }
+}
+
+impl BossDescriptor {
+    //  As EnemyDescriptor::with_overrides
+    pub fn with_overrides(descr: &Rc<Self>, fields: &Object) -> Result<Rc<Self>, Error> {
+        if fields.is_empty() {
+            return Ok(descr.clone());
+        }
+        BossDescriptor::from_json(merge_fields(&descr.base, fields))
+    }
+
+    fn from_json(obj: Object) -> Result<Rc<Self>, Error> {
+        check_schema("boss", &schema::BOSS, &obj)?;
+
+        let body_path = get_string("boss", &obj, "body")?;
+        let body = EnemyDescriptor::new(Path::new(&body_path))?;
+
+        let mut phases = Vec::new();
+        for (i, poss_phase) in get_array("boss", &obj, "phases")?
+            .iter()
+            .enumerate() {
+            let phase_obj = poss_phase.as_object()
+                .ok_or(error_simple("boss",
+                                    format!("phase {} not well formed", i)
+                                        .as_str()))?;
+            phases.push(BossPhase::from_json(phase_obj)?);
+        }
+        if phases.is_empty() {
+            return Err(error_simple("boss", "needs at least one phase"));
+        }
+        //  Sort so the first phase is the one used at full health
+        phases.sort_by(|a, b| {
+            b.hp_fraction.partial_cmp(&a.hp_fraction).unwrap()
+        });
+
+        Ok(Rc::new(BossDescriptor {
+            name: get_string("boss", &obj, "name")?,
+            body: body,
+            hp: get_float("boss", &obj, "hp")?,
+            phases: phases,
+            base: obj,
+        }))
+    }
+}
+
+//  Animations and an animator for a scripted object, eg. descriptors/anim/*.json
+//  "animations" maps names to {"sheet", "tag"} or {"frames", "path"} objects,
+//  numbered pngs being shown at "speed" frames a second
+pub struct AnimatedDescriptor {
+    pub animations: AnimationSet,
+    pub animator: Rc<AnimatorDescriptor>,
+}
+
+impl Descriptor for AnimatedDescriptor {
+    fn new(json_path: &Path) -> Result<Rc<Self>, Error> {
+        let obj = load_descriptor_json("animated", &schema::ANIMATED, json_path)?;
+        let speed = get_float_or("animated", &obj, "speed", 10.0)?;
+
+        let mut sheets = SheetCache::new();
+        let mut animations = AnimationSet::new();
+        let specs = obj.get("animations")
+            .and_then(|a| a.as_object())
+            .ok_or(error_simple("animated", "has no 'animations' object"))?;
+        for (name, spec) in specs {
+            let spec = spec.as_object()
+                .ok_or(error_simple("animated",
+                                    format!("animation {} not well formed", name)
+                                        .as_str()))?;
+            animations.insert(name.clone(),
+                              get_animation_fields("animated", spec, "", speed, &mut sheets)?);
+        }
+        let animator = get_animator("animated", &obj, &animations)?;
+
+        Ok(Rc::new(AnimatedDescriptor {
+            animations: animations,
+            animator: animator,
+        }))
+    }
+}
@@ -0,0 +1,199 @@
+use draw::Color;
+use graphics::{Context, Graphics};
+use graphics::math::Matrix2d;
+use graphics::triangulation::{rect_tri_list_uv, rect_tri_list_xy};
+use opengl_graphics::{GlGraphics, Texture};
+use piston::input::*;
+use shaders::{NoisyShader, Program};
+use std::rc::Rc;
+
+//  Vertices handed to the backend at once, 170 quads fits its buffer
+const CHUNK_VERTICES: usize = 170 * 6;
+
+//  What a frame cost to draw
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DrawStats {
+    //  Submissions of queued sprites
+    pub batches: usize,
+    pub sprites: usize,
+    //  Things that drew themselves instead of queueing
+    pub direct: usize,
+}
+
+impl DrawStats {
+    //  Each direct draw is at least one call
+    pub fn draw_calls(&self) -> usize {
+        self.batches + self.direct
+    }
+}
+
+struct Quad {
+    texture: Rc<Texture>,
+    program: Program,
+    color: Color,
+}
+
+/*
+ * Sprites queued over a frame, drawn in order but with every run of quads
+ * sharing a texture, program and color submitted together. With sprites
+ * packed into atlas pages most of a frame is a handful of runs.
+ *
+ * Anything drawing directly should flush first so it stays on top of what
+ * was queued before it.
+ */
+pub struct SpriteBatch {
+    context: Context,
+    program: Program,
+    quads: Vec<Quad>,
+    //  Six per quad
+    xy: Vec<[f32; 2]>,
+    uv: Vec<[f32; 2]>,
+    pub stats: DrawStats,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        SpriteBatch {
+            context: Context::new(),
+            program: Program::Colored,
+            quads: Vec::new(),
+            xy: Vec::new(),
+            uv: Vec::new(),
+            stats: DrawStats::default(),
+        }
+    }
+
+    //  Start a frame, clearing anything left over and the stats
+    pub fn begin(&mut self, args: &RenderArgs) {
+        self.context = Context::new_viewport(args.viewport());
+        self.quads.clear();
+        self.xy.clear();
+        self.uv.clear();
+        self.stats = DrawStats::default();
+    }
+
+    //  The context GlGraphics would give a draw this frame, for transforms
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    //  Program quads pushed after this are drawn with
+    pub fn set_program(&mut self, program: Program) {
+        self.program = program;
+    }
+
+    //  Queue the src part of texture drawn over rect, as Image would
+    pub fn push(&mut self,
+                texture: &Rc<Texture>,
+                color: Color,
+                src: [f64; 4],
+                rect: [f64; 4],
+                transform: Matrix2d) {
+        self.xy.extend_from_slice(&rect_tri_list_xy(transform, rect));
+        self.uv.extend_from_slice(&rect_tri_list_uv(&**texture, src));
+        self.quads.push(Quad {
+            texture: texture.clone(),
+            program: self.program,
+            color: color,
+        });
+        self.stats.sprites += 1;
+    }
+
+    //  Draw everything queued, returns false if there was nothing
+    //  Leaves the last run's program in use
+    pub fn flush(&mut self,
+                 args: &RenderArgs,
+                 ctx: &mut GlGraphics,
+                 shader: &NoisyShader)
+                 -> bool {
+        if self.quads.is_empty() {
+            return false;
+        }
+        let mut program = None;
+        let mut start = 0;
+        while start < self.quads.len() {
+            let end = {
+                let first = &self.quads[start];
+                self.quads[start..]
+                    .iter()
+                    .position(|q| {
+                        q.texture.get_id() != first.texture.get_id() ||
+                        q.program != first.program ||
+                        q.color != first.color
+                    })
+                    .map_or(self.quads.len(), |n| start + n)
+            };
+            let run = &self.quads[start];
+            if program != Some(run.program) {
+                shader.set_program(run.program, ctx);
+                program = Some(run.program);
+            }
+            let xy = &self.xy[start * 6..end * 6];
+            let uv = &self.uv[start * 6..end * 6];
+            ctx.draw(args.viewport(), |c, gl| {
+                gl.tri_list_uv(&c.draw_state, &run.color, &*run.texture, |f| {
+                    for (xy, uv) in xy.chunks(CHUNK_VERTICES).zip(uv.chunks(CHUNK_VERTICES)) {
+                        f(xy, uv);
+                    }
+                });
+            });
+            self.stats.batches += (xy.len() + CHUNK_VERTICES - 1) / CHUNK_VERTICES;
+            start = end;
+        }
+        self.quads.clear();
+        self.xy.clear();
+        self.uv.clear();
+        true
+    }
+}
+
+//  Draw stats summed over frames, for --draw-stats runs
+pub struct DrawProfile {
+    frames: usize,
+    total: DrawStats,
+    max_draw_calls: usize,
+    //  Sprites and pages in the atlas, see atlas.rs
+    atlas: (usize, usize),
+}
+
+impl DrawProfile {
+    pub fn new() -> Self {
+        DrawProfile {
+            frames: 0,
+            total: DrawStats::default(),
+            max_draw_calls: 0,
+            atlas: (0, 0),
+        }
+    }
+
+    pub fn add(&mut self, stats: &DrawStats) {
+        self.frames += 1;
+        self.total.batches += stats.batches;
+        self.total.sprites += stats.sprites;
+        self.total.direct += stats.direct;
+        self.max_draw_calls = self.max_draw_calls.max(stats.draw_calls());
+    }
+
+    pub fn set_atlas(&mut self, sprites: usize, pages: usize) {
+        self.atlas = (sprites, pages);
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    //  One line of per frame averages, easy to grep out of a run's output
+    pub fn report(&self) -> String {
+        let per_frame = |n: usize| n as f64 / (self.frames.max(1) as f64);
+        format!("draw-stats frames {} draw_calls {:.1} max_draw_calls {} batches {:.1} \
+                 sprites {:.1} direct {:.1} atlas_sprites {} atlas_pages {}",
+                self.frames,
+                per_frame(self.total.draw_calls()),
+                self.max_draw_calls,
+                per_frame(self.total.batches),
+                per_frame(self.total.sprites),
+                per_frame(self.total.direct),
+                self.atlas.0,
+                self.atlas.1)
+    }
+}
@@ -1,3 +1,6 @@
+extern crate rand;
+
+use self::rand::{Rng, thread_rng};
 use game::{Height, Pos, Width, fphys};
 use graphics::*;
 use piston_window::types::Matrix2d;
@@ -12,7 +15,7 @@ use std::f64::EPSILON;
 pub struct CameraId(usize);
 
 #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-pub struct CameraPriority(u32);
+pub struct CameraPriority(pub u32);
 
 #[derive(Copy, Clone)]
 pub struct ViewTransform {
@@ -71,11 +74,53 @@ impl PartialOrd for IdCamera {
     }
 }
 
+//  Short lived effect that fades out linearly over its duration
+#[derive(Clone, Copy)]
+struct Effect {
+    strength : fphys,
+    duration : fphys,
+    left : fphys,
+}
+
+impl Effect {
+    fn none() -> Self {
+        Effect {
+            strength : 0.0,
+            duration : 0.0,
+            left : 0.0,
+        }
+    }
+
+    fn new(strength : fphys, duration : fphys) -> Self {
+        Effect {
+            strength : strength,
+            duration : duration,
+            left : duration,
+        }
+    }
+
+    fn tick(&mut self, dt : fphys) {
+        self.left = (self.left - dt).max(0.0);
+    }
+
+    fn current(&self) -> fphys {
+        if self.duration > 0.0 {
+            self.strength * self.left / self.duration
+        } else {
+            0.0
+        }
+    }
+}
+
 pub struct Editor {
     cameras : BinaryHeap<IdCamera>,
     transform : ViewTransform,
     current_id : CameraId,
     base_width : fphys,
+    //  Applied on top of the camera transform, so they don't disturb lerping
+    shake : Effect,
+    shake_offset : (fphys, fphys),
+    punch : Effect,
 }
 
 
@@ -89,6 +134,9 @@ impl Editor {
             transform : ViewTransform {x : 0.0, y : 0.0, scale : 1.0},
             base_width : 800.0,
             current_id : current_id,
+            shake : Effect::none(),
+            shake_offset : (0.0, 0.0),
+            punch : Effect::none(),
         }
     }
     pub fn add_camera(&mut self, camera : Box<Camera>) -> CameraId {
@@ -105,7 +153,32 @@ impl Editor {
         self.cameras = cs;
     }
     pub fn transform(&self) -> ViewTransform {
-        self.transform
+        let (sx, sy) = self.shake_offset;
+        ViewTransform {
+            x : self.transform.x + sx,
+            y : self.transform.y + sy,
+            scale : self.transform.scale * (1.0 + self.punch.current()),
+        }
+    }
+    //  Shake the view by up to intensity world units
+    pub fn shake(&mut self, intensity : fphys, duration : fphys) {
+        self.shake = Effect::new(intensity, duration);
+    }
+    //  Zoom in by amount (0.1 is 10%) then ease back out
+    pub fn punch_zoom(&mut self, amount : fphys, duration : fphys) {
+        self.punch = Effect::new(amount, duration);
+    }
+    pub fn tick(&mut self, dt : fphys) {
+        self.shake.tick(dt);
+        self.punch.tick(dt);
+        let intensity = self.shake.current();
+        self.shake_offset = if intensity > 0.0 {
+            let mut rng = thread_rng();
+            (rng.gen_range(-intensity, intensity),
+             rng.gen_range(-intensity, intensity))
+        } else {
+            (0.0, 0.0)
+        };
     }
     pub fn update(&mut self, viewport : &Viewport, world : &World) {
 
@@ -169,6 +242,48 @@ impl Camera for ViewStatic {
     }
 }
 
+//  Fixed camera showing the whole of a rectangle, eg. a boss arena
+pub struct ViewArena {
+    rect : Rectangle,
+    weight : fphys,
+    priority : CameraPriority,
+}
+
+impl ViewArena {
+    pub fn new(rect : Rectangle, weight : fphys, priority : CameraPriority) -> Self {
+        ViewArena {
+            rect : rect,
+            weight : weight,
+            priority : priority,
+        }
+    }
+}
+
+impl Camera for ViewArena {
+    fn transform(&self, viewport : &Viewport) -> ViewTransform {
+        let view_width = viewport.rect[2] as f64;
+        let view_height = viewport.rect[3] as f64;
+        let r = &self.rect;
+        //  Fit whichever dimension is tighter
+        let scale = (view_width / r.w).min(view_height / r.h);
+        ViewTransform {
+            x: r.x + r.w / 2.0,
+            y: r.y + r.h / 2.0,
+            scale: scale,
+        }
+    }
+    fn lerp_pos_weight(&self) -> fphys {
+        self.weight
+    }
+    fn lerp_scale_weight(&self) -> fphys {
+        self.weight
+    }
+    fn update(&mut self, _ : &World) {}
+    fn priority(&self) -> CameraPriority {
+        self.priority
+    }
+}
+
 pub struct ViewFollower {
     follow_id: u32,
     priority : CameraPriority,
@@ -182,6 +297,8 @@ pub struct ViewFollower {
 
     offset_factor: fphys,
     scale_mult: fphys,
+    //  Multiplies the speed based scale
+    zoom: fphys,
 
     follow_prev_x: fphys,
     follow_prev_y: fphys,
@@ -200,10 +317,25 @@ impl ViewFollower {
             w_scale: 200.0,
             offset_factor: 00.0,
             scale_mult: 0.035,
+            zoom: 1.0,
             follow_prev_x: 0.0,
             follow_prev_y: 0.0,
         }
     }
+    pub fn new(id: u32,
+               priority: CameraPriority,
+               w: fphys,
+               w_scale: fphys,
+               zoom: fphys)
+               -> Self {
+        ViewFollower {
+            priority: priority,
+            w: w,
+            w_scale: w_scale,
+            zoom: zoom,
+            ..ViewFollower::new_defaults(ViewTransform {x: 0.0, y: 0.0, scale: 1.0}, id)
+        }
+    }
 }
 impl Camera for ViewFollower {
     fn priority(&self) -> CameraPriority {
@@ -237,7 +369,7 @@ impl Camera for ViewFollower {
             self.x_offset = bbx + x_offset;
             self.y_offset = bby + y_offset;
 
-            self.scale = 0.8 - speed * self.scale_mult;
+            self.scale = (0.8 - speed * self.scale_mult) * self.zoom;
 
             self.follow_prev_x = bbx;
             self.follow_prev_y = bby;
@@ -246,6 +378,16 @@ impl Camera for ViewFollower {
 }
 
 impl ViewTransform {
+    //  Inverse of transform, for mapping screen positions into the world
+    pub fn screen_to_world(&self,
+                           screen_x: fphys,
+                           screen_y: fphys,
+                           screen_width: fphys,
+                           screen_height: fphys)
+                           -> Pos {
+        Pos((screen_x - screen_width / 2.0) / self.scale + self.x,
+            (screen_y - screen_height / 2.0) / self.scale + self.y)
+    }
     pub fn to_rectangle(&self,
                         screen_width: fphys,
                         screen_height: fphys)
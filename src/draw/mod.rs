@@ -11,6 +11,8 @@ use world::World;
 
 pub mod camera;
 pub use self::camera::*;
+pub mod batch;
+pub use self::batch::*;
 
 pub type Color = [f32; 4];
 
@@ -37,6 +39,11 @@ pub trait Drawable {
     fn set_position(&mut self, Pos);
     fn set_color(&mut self, Color);
     fn should_draw(&self, &Rectangle) -> bool;
+    //  Queue sprites into the frame's batch instead of drawing, false for
+    //  things that can only draw themselves
+    fn queue(&mut self, _: &mut SpriteBatch, _: &ViewTransform) -> bool {
+        false
+    }
 }
 
 pub struct GrphxContainer {
@@ -0,0 +1,69 @@
+// Animators for scripted objects
+//
+// A script attaches one with (animator "descriptors/anim/x.json"), the
+// format being that of AnimatedDescriptor, sets its parameters with
+// (anim-set name value) and draws it with (draw-animator x y).
+// It is stepped before each tick and its events call
+// (anim-event state state-name event) in the script.
+
+use animator::Animator;
+use descriptors::{AnimatedDescriptor, Descriptor};
+use game::{Id, fphys};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::Path;
+use std::rc::Rc;
+
+use ketos::{ExecError, Value};
+
+pub struct ScriptAnimator {
+    pub path : String,
+    pub descr : Rc<AnimatedDescriptor>,
+    pub animator : Animator,
+}
+
+pub type Animators = Rc<RefCell<HashMap<Id, ScriptAnimator>>>;
+
+// Descriptors by path, shared by every object using the same one
+pub type AnimatedCache = Rc<RefCell<HashMap<String, Rc<AnimatedDescriptor>>>>;
+
+pub fn load_animated(cache : &AnimatedCache, path : &str) -> Result<Rc<AnimatedDescriptor>, Error> {
+    if let Some(descr) = cache.borrow().get(path) {
+        return Ok(descr.clone());
+    }
+    let descr = AnimatedDescriptor::new(Path::new(path))?;
+    cache.borrow_mut().insert(path.to_owned(), descr.clone());
+    Ok(descr)
+}
+
+// Reload a changed descriptor into the cache and every animator using it
+// Returns false if path isn't one scripts have loaded
+pub fn reload_animated(cache : &AnimatedCache, animators : &Animators, path : &Path) -> bool {
+    let key = match cache.borrow().keys().find(|k| path.ends_with(Path::new(k.as_str()))) {
+        Some(k) => k.clone(),
+        None => return false,
+    };
+    match AnimatedDescriptor::new(Path::new(&key)) {
+        Ok(descr) => {
+            for a in animators.borrow_mut().values_mut().filter(|a| a.path == key) {
+                a.descr = descr.clone();
+                a.animator.set_descriptor(descr.animator.clone());
+            }
+            cache.borrow_mut().insert(key.clone(), descr);
+            println!("Reloaded {}", key);
+        }
+        Err(e) => println!("Keeping previous version of {}: {}", key, e),
+    }
+    true
+}
+
+// Parameters are numbers, booleans are stored as 1 and 0
+pub fn param_from_value(value : &Value) -> Result<fphys, ExecError> {
+    match *value {
+        Value::Bool(b) => Ok(if b { 1.0 } else { 0.0 }),
+        Value::Float(f) => Ok(f),
+        Value::Integer(ref i) => Ok(i.to_f64().unwrap_or(0.0)),
+        ref other => Err(ExecError::expected("number or bool", other)),
+    }
+}
@@ -0,0 +1,219 @@
+// Developer console for evaluating lisp while the game is running
+//
+// Toggled with the backquote key. Lines starting with ':' are console
+// commands, everything else is evaluated by the DynMap:
+//  :target <script>   evaluate inside scripts/<script>.lisp
+//  :global            evaluate in the shared console interpreter
+//  :clear             clear the output
+
+use draw::*;
+use game::{Pos, fphys};
+use opengl_graphics::{Filter, GlGraphics, GlyphCache};
+use piston::input::*;
+use piston_window::TextureSettings;
+use std::path::Path;
+
+use super::DynMap;
+
+const CONSOLE_CHAR_SIZE: u32 = 16;
+const CONSOLE_LINE_HEIGHT: fphys = 18.0;
+const CONSOLE_MAX_OUTPUT: usize = 200;
+const CONSOLE_MAX_HISTORY: usize = 100;
+// Fraction of the screen covered when open
+const CONSOLE_HEIGHT: fphys = 0.4;
+
+const CONSOLE_BG: Color = [0.0, 0.0, 0.0, 0.75];
+const CONSOLE_TEXT: Color = [0.9, 0.9, 0.9, 1.0];
+const CONSOLE_RESULT: Color = [0.6, 1.0, 0.6, 1.0];
+const CONSOLE_ERROR: Color = [1.0, 0.4, 0.4, 1.0];
+
+#[derive(Clone, Copy)]
+enum LineKind {
+    Input,
+    Result,
+    Error,
+}
+
+pub struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+    // Position when scrolling back through history
+    history_pos: Option<usize>,
+    output: Vec<(LineKind, String)>,
+    // Script file to evaluate in, None for the global interpreter
+    target: Option<String>,
+    char_cache: GlyphCache<'static>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let mut ts = TextureSettings::new();
+        ts.set_mag(Filter::Nearest);
+        Console {
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            output: Vec::new(),
+            target: None,
+            char_cache: GlyphCache::new(Path::new("fonts/alterebro.ttf"), (), ts)
+                .unwrap(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.input.extend(text.chars().filter(|&c| c != '`' && !c.is_control()));
+    }
+
+    pub fn press(&mut self, button: Button, dyn_map: &mut DynMap) {
+        match button {
+            Button::Keyboard(Key::Return) => {
+                let line = self.input.clone();
+                self.input.clear();
+                self.history_pos = None;
+                if !line.trim().is_empty() {
+                    self.submit(line, dyn_map);
+                }
+            }
+            Button::Keyboard(Key::Backspace) => {
+                self.input.pop();
+            }
+            Button::Keyboard(Key::Up) => {
+                let pos = match self.history_pos {
+                    Some(p) if p > 0 => p - 1,
+                    Some(p) => p,
+                    None if !self.history.is_empty() => self.history.len() - 1,
+                    None => return,
+                };
+                self.history_pos = Some(pos);
+                self.input = self.history[pos].clone();
+            }
+            Button::Keyboard(Key::Down) => {
+                match self.history_pos {
+                    Some(p) if p + 1 < self.history.len() => {
+                        self.history_pos = Some(p + 1);
+                        self.input = self.history[p + 1].clone();
+                    }
+                    _ => {
+                        self.history_pos = None;
+                        self.input.clear();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn submit(&mut self, line: String, dyn_map: &mut DynMap) {
+        self.push_output(LineKind::Input, format!("{}{}", self.prompt(), line));
+        self.history.push(line.clone());
+        if self.history.len() > CONSOLE_MAX_HISTORY {
+            self.history.remove(0);
+        }
+
+        let trimmed = line.trim();
+        if trimmed.starts_with(':') {
+            self.command(trimmed);
+            return;
+        }
+
+        match dyn_map.eval_console(self.target.as_ref().map(|t| t.as_str()), &line) {
+            Ok(result) => self.push_output(LineKind::Result, result),
+            Err(error) => self.push_output(LineKind::Error, error),
+        }
+    }
+
+    fn command(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(":target"), Some(script)) => {
+                self.target = Some(format!("scripts/{}.lisp", script));
+            }
+            (Some(":global"), None) => {
+                self.target = None;
+            }
+            (Some(":clear"), None) => {
+                self.output.clear();
+            }
+            _ => {
+                self.push_output(LineKind::Error,
+                                 format!("Unknown command {}", line));
+            }
+        }
+    }
+
+    fn push_output(&mut self, kind: LineKind, text: String) {
+        // Multi line results are split so each gets its own row
+        for l in text.lines() {
+            self.output.push((kind, l.to_owned()));
+        }
+        if self.output.len() > CONSOLE_MAX_OUTPUT {
+            let extra = self.output.len() - CONSOLE_MAX_OUTPUT;
+            self.output.drain(..extra);
+        }
+    }
+
+    fn prompt(&self) -> String {
+        match self.target {
+            Some(ref t) => format!("[{}]> ", t),
+            None => "> ".to_owned(),
+        }
+    }
+}
+
+impl Drawable for Console {
+    fn draw(&mut self,
+            args: &RenderArgs,
+            ctx: &mut GlGraphics,
+            _: &ViewTransform) {
+        use graphics::*;
+        if !self.open {
+            return;
+        }
+        let viewr = args.viewport().rect;
+        let w = viewr[2] as fphys;
+        let h = viewr[3] as fphys * CONSOLE_HEIGHT;
+        let rows = (h / CONSOLE_LINE_HEIGHT).floor() as usize;
+        let prompt_line = format!("{}{}_", self.prompt(), self.input);
+
+        let visible = rows.saturating_sub(1).min(self.output.len());
+        let start = self.output.len() - visible;
+
+        let output = &self.output;
+        let char_cache = &mut self.char_cache;
+        ctx.draw(args.viewport(), |c, gl| {
+            rectangle(CONSOLE_BG, [0.0, 0.0, w, h], c.transform, gl);
+            let mut text = Text::new(CONSOLE_CHAR_SIZE);
+            for (i, &(ref kind, ref line)) in output[start..].iter().enumerate() {
+                text.color = match *kind {
+                    LineKind::Input => CONSOLE_TEXT,
+                    LineKind::Result => CONSOLE_RESULT,
+                    LineKind::Error => CONSOLE_ERROR,
+                };
+                let transform = c.transform
+                    .trans(4.0, (i + 1) as fphys * CONSOLE_LINE_HEIGHT);
+                let _ = text.draw(line, char_cache, &c.draw_state, transform, gl);
+            }
+            text.color = CONSOLE_TEXT;
+            let transform = c.transform.trans(4.0, h - 4.0);
+            let _ = text.draw(&prompt_line, char_cache, &c.draw_state, transform, gl);
+        });
+    }
+
+    fn set_position(&mut self, _: Pos) {}
+
+    fn set_color(&mut self, _: Color) {}
+
+    fn should_draw(&self, _: &Rectangle) -> bool {
+        self.open
+    }
+}
@@ -0,0 +1,72 @@
+// Errors raised by scripts, collected so they can be shown on screen
+//
+// Each distinct error is kept once per script with a count of how often
+// it happened, so an error in tick doesn't flood the output every frame.
+
+#[derive(Clone)]
+pub struct ScriptError {
+    pub script : String,
+    pub line : Option<u32>,
+    pub message : String,
+    pub count : u32,
+    // Hidden from the overlay, a reload clears it so new failures show
+    pub dismissed : bool,
+}
+
+pub struct ScriptErrors {
+    errors : Vec<ScriptError>,
+}
+
+// Ketos prefixes located errors with file:line:col
+fn parse_line(script : &str, message : &str) -> Option<u32> {
+    let prefix = format!("{}:", script);
+    message.find(&prefix)
+        .and_then(|i| {
+            message[i + prefix.len()..]
+                .split(':')
+                .next()
+                .and_then(|line| line.trim().parse().ok())
+        })
+}
+
+impl ScriptErrors {
+    pub fn new() -> Self {
+        ScriptErrors { errors : Vec::new() }
+    }
+
+    // Record an error, returning true the first time it is seen
+    pub fn report(&mut self, script : &str, message : String) -> bool {
+        if let Some(e) = self.errors.iter_mut()
+            .find(|e| e.script == script && e.message == message) {
+            e.count += 1;
+            return false;
+        }
+        self.errors.push(ScriptError {
+            script : script.to_owned(),
+            line : parse_line(script, &message),
+            message : message,
+            count : 1,
+            dismissed : false,
+        });
+        true
+    }
+
+    // Forget a script's errors, eg. once it reloads cleanly
+    pub fn clear_script(&mut self, script : &str) {
+        self.errors.retain(|e| e.script != script);
+    }
+
+    pub fn dismiss(&mut self) {
+        for e in &mut self.errors {
+            e.dismissed = true;
+        }
+    }
+
+    pub fn all(&self) -> &[ScriptError] {
+        &self.errors
+    }
+
+    pub fn visible(&self) -> Vec<ScriptError> {
+        self.errors.iter().filter(|e| !e.dismissed).cloned().collect()
+    }
+}
@@ -3,6 +3,7 @@ use draw::*;
 use piston::input::*;
 use opengl_graphics::GlGraphics;
 use opengl_graphics::GlyphCache;
+use opengl_graphics::Texture;
 use opengl_graphics::Filter;
 use graphics::Viewport;
 use std::cmp::Ordering;
@@ -12,7 +13,11 @@ use piston_window::TextureSettings;
 use std::sync::{Arc, Mutex};
 use std::cell::RefCell;
 
-use super::DynMap;
+use super::{DynMap, sender_id};
+use super::animation::Animators;
+use animator::{AnimLayer, draw_layers};
+use atlas::{ATLAS_DIR, AtlasSprite, load_sprite};
+use descriptors::AnimatedDescriptor;
 
 use std::rc::Rc;
 use std::cell::RefMut;
@@ -30,6 +35,9 @@ pub struct Font {
 pub struct ResourceContext {
     pub fonts : HashMap<String, RefCell<Font>>,
     default_font : String,
+    // Found in sprites/ the first time a script draws them, mostly packed
+    // into the atlas
+    sprites : RefCell<HashMap<String, Option<AtlasSprite>>>,
 }
 
 impl ResourceContext {
@@ -50,14 +58,45 @@ impl ResourceContext {
         ResourceContext {
             fonts : map,
             default_font : default_fontname.to_owned(),
+            sprites : RefCell::new(HashMap::new()),
         }
     }
+
+    fn font(&self, name : &str) -> &RefCell<Font> {
+        self.fonts.get(name)
+            .unwrap_or_else(|| self.fonts.get(&self.default_font).unwrap())
+    }
+
+    // Missing sprites are remembered so the disk isn't hit every frame
+    fn sprite(&self, name : &str) -> Option<AtlasSprite> {
+        let mut sprites = self.sprites.borrow_mut();
+        sprites.entry(name.to_owned())
+            .or_insert_with(|| {
+                let path = Path::new(ATLAS_DIR).join(name);
+                match load_sprite(&path) {
+                    Ok(sprite) => Some(sprite),
+                    Err(e) => {
+                        println!("Could not load sprite {}: {}", name, e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
 }
 
 #[derive(Clone)]
 pub struct GraphicsContext {
     pub color : Color,
     pub font : String,
+    // Applied around each primitive's position, in radians
+    pub rotation : fphys,
+    pub scale : (fphys, fphys),
+    pub line_width : fphys,
+    // Draw in screen pixels instead of world coordinates
+    pub screen : bool,
+    // Higher layers are drawn on top
+    pub layer : i64,
 }
 
 impl GraphicsContext {
@@ -66,8 +105,24 @@ impl GraphicsContext {
         GraphicsContext {
             color : BLACK,
             font : "fnt_basic".to_owned(),
+            rotation : 0.0,
+            scale : (1.0, 1.0),
+            line_width : 1.0,
+            screen : false,
+            layer : 0,
         }
     }
+
+    // Transform placing the origin at (x, y) in world or screen space
+    fn transform(&self, x : fphys, y : fphys, vt : &ViewTransform, c : &::graphics::Context) -> ::graphics::math::Matrix2d {
+        use graphics::Transformed;
+        let base = if self.screen {
+            c.transform.trans(x, y)
+        } else {
+            vt.transform(x, y, 1.0, 1.0, c)
+        };
+        base.rot_rad(self.rotation).scale(self.scale.0, self.scale.1)
+    }
 }
 
 pub struct GraphicsCallback {
@@ -93,8 +148,16 @@ impl DynGraphics {
 }
 
 pub enum GraphicPrim {
-    Rect(fphys, fphys, fphys, fphys),
+    // Shapes carry whether they are outlined rather than filled
+    Rect(fphys, fphys, fphys, fphys, bool),
     Text(fphys, fphys, String),
+    Line(fphys, fphys, fphys, fphys),
+    Circle(fphys, fphys, fphys, bool),
+    // Points relative to the first one, which is the origin
+    Polygon(Vec<[fphys; 2]>, bool),
+    Sprite(fphys, fphys, String),
+    // Frames of an object's animator as they were when it drew
+    Animation(fphys, fphys, Rc<AnimatedDescriptor>, Vec<AnimLayer>),
 }
 
 
@@ -106,26 +169,86 @@ impl GraphicPrim {
             ctx : &mut GlGraphics,
             vt : &ViewTransform) {
         use graphics::*;
+        let gc = graphics_context;
+        let color = gc.color;
+        let border = gc.line_width / 2.0;
         match self {
-            GraphicPrim::Rect(x, y, w, h) => {
+            GraphicPrim::Rect(x, y, w, h, outline) => {
                 ctx.draw(args.viewport(), |c, gl| {
-                    let transform = vt.transform(*x, *y, *w, *h, &c);
-                                               //.scale(vt.scale, vt.scale)
-                                               //.trans(-vt.x, -vt.y);
-                                               ;
-                    rectangle(graphics_context.color, [0.0, 0.0, 1.0, 1.0], transform, gl);
+                    let transform = gc.transform(*x, *y, vt, &c);
+                    let shape = if *outline {
+                        Rectangle::new_border(color, border)
+                    } else {
+                        Rectangle::new(color)
+                    };
+                    shape.draw([0.0, 0.0, *w, *h], &c.draw_state, transform, gl);
                 });
             },
             GraphicPrim::Text(x, y, t) => {
-                let font_name = &graphics_context.font;
-                let font = resource_context.fonts.get(font_name).unwrap();
+                let font = resource_context.font(&gc.font);
                 let mut text = Text::new(font.borrow().char_size);
-                text.color = graphics_context.color;
+                text.color = color;
                 ctx.draw(args.viewport(), |c, gl| {
-                    let transform = vt.transform(*x, *y, 1.0, 1.0, &c);
+                    let transform = gc.transform(*x, *y, vt, &c);
                     text.draw(t, &mut font.borrow_mut().char_cache, &c.draw_state, transform, gl).unwrap();
                 });
             },
+            GraphicPrim::Line(x1, y1, x2, y2) => {
+                ctx.draw(args.viewport(), |c, gl| {
+                    let transform = gc.transform(*x1, *y1, vt, &c);
+                    line(color, border, [0.0, 0.0, x2 - x1, y2 - y1], transform, gl);
+                });
+            },
+            GraphicPrim::Circle(x, y, radius, outline) => {
+                ctx.draw(args.viewport(), |c, gl| {
+                    let transform = gc.transform(*x, *y, vt, &c);
+                    let shape = if *outline {
+                        Ellipse::new_border(color, border)
+                    } else {
+                        Ellipse::new(color)
+                    };
+                    let rect = [-radius, -radius, 2.0 * radius, 2.0 * radius];
+                    shape.draw(rect, &c.draw_state, transform, gl);
+                });
+            },
+            GraphicPrim::Polygon(points, outline) => {
+                if points.len() < 2 {
+                    return;
+                }
+                let [ox, oy] = points[0];
+                let relative = points.iter()
+                    .map(|&[px, py]| [px - ox, py - oy])
+                    .collect::<Vec<[fphys; 2]>>();
+                ctx.draw(args.viewport(), |c, gl| {
+                    let transform = gc.transform(ox, oy, vt, &c);
+                    if *outline {
+                        let n = relative.len();
+                        for i in 0..n {
+                            let [ax, ay] = relative[i];
+                            let [bx, by] = relative[(i + 1) % n];
+                            line(color, border, [ax, ay, bx, by], transform, gl);
+                        }
+                    } else {
+                        polygon(color, &relative, transform, gl);
+                    }
+                });
+            },
+            GraphicPrim::Sprite(x, y, name) => {
+                if let Some(sprite) = resource_context.sprite(name) {
+                    ctx.draw(args.viewport(), |c, gl| {
+                        let transform = gc.transform(*x, *y, vt, &c);
+                        Image::new_color(color)
+                            .src_rect(sprite.src)
+                            .draw(&*sprite.texture, &c.draw_state, transform, gl);
+                    });
+                }
+            },
+            GraphicPrim::Animation(x, y, descr, layers) => {
+                ctx.draw(args.viewport(), |c, gl| {
+                    let transform = gc.transform(*x, *y, vt, &c);
+                    draw_layers(&descr.animations, layers, 0.0, color[3], &c.draw_state, transform, gl);
+                });
+            },
         }
     }
 }
@@ -151,7 +274,7 @@ impl Drawable for DynGraphics {
 
         {
             let mut map = self.dyn_map.lock().unwrap();
-            map.run_draw(self.id, &self.logic_name, rargs, graphics, vt);
+            map.run_draw(self.id, &self.logic_name);
         }
     }
     fn set_position(&mut self, p : Pos) {
@@ -164,12 +287,54 @@ impl Drawable for DynGraphics {
 }
 
 
-pub fn add_graphic_funs(scope : &GlobalScope, c : &Rc<RefCell<Vec<GraphicQueued>>>) {
+pub fn add_graphic_funs(scope : &GlobalScope, c : &Rc<RefCell<Vec<GraphicQueued>>>, animators : &Animators) {
     let gc = Rc::new(RefCell::new(GraphicsContext::new()));
     add_graph_fun!(c, gc, scope, "draw-text", add_text, 3);
     add_graph_fun!(c, gc, scope, "draw-rectangle", add_rectangle, 5);
+    add_graph_fun!(c, gc, scope, "draw-line", add_line, 4);
+    add_graph_fun!(c, gc, scope, "draw-circle", add_circle, 4);
+    add_graph_fun!(c, gc, scope, "draw-polygon", add_polygon, 2);
+    add_graph_fun!(c, gc, scope, "draw-sprite", add_sprite, 3);
     add_context_mut!(gc, scope, "draw-set-color", set_color, 3);
     add_context_mut!(gc, scope, "draw-set-alpha", set_alpha, 1);
+    add_context_mut!(gc, scope, "draw-set-font", set_font, 1);
+    add_context_mut!(gc, scope, "draw-set-rotation", set_rotation, 1);
+    add_context_mut!(gc, scope, "draw-set-scale", set_scale, 2);
+    add_context_mut!(gc, scope, "draw-set-line-width", set_line_width, 1);
+    add_context_mut!(gc, scope, "draw-set-screen", set_screen, 1);
+    add_context_mut!(gc, scope, "draw-set-layer", set_layer, 1);
+    add_animator_fun(scope, c, &gc, animators);
+}
+
+// (draw-animator x y) draws the object's animator with its top left at x y
+fn add_animator_fun(scope : &GlobalScope,
+                    c : &Rc<RefCell<Vec<GraphicQueued>>>,
+                    gc : &Rc<RefCell<GraphicsContext>>,
+                    animators : &Animators) {
+    let c_tmp = c.clone();
+    let gc_tmp = gc.clone();
+    let animators = animators.clone();
+    scope.add_value_with_name("draw-animator", move |lisp_name| {
+        Value::new_foreign_fn(lisp_name, move |scope, args| {
+            if args.len() == 2 {
+                let x : f64 = FromValueRef::from_value_ref(&args[0])?;
+                let y : f64 = FromValueRef::from_value_ref(&args[1])?;
+                if let Some(a) = animators.borrow().get(&sender_id(scope)) {
+                    let layers = a.animator.layers(&a.descr.animations);
+                    let prim = GraphicPrim::Animation(x, y, a.descr.clone(), layers);
+                    c_tmp.borrow_mut().push(GraphicQueued(prim, gc_tmp.borrow().clone()));
+                }
+                Ok(Value::Unit)
+            }
+            else {
+                Err(From::from(ExecError::ArityError{
+                    name: Some(lisp_name),
+                    expected: Arity::Exact(2 as u32),
+                    found: args.len() as u32,
+                }))
+            }
+        })
+    });
 }
 
 pub fn get_graphics_variables(map : &mut HashMap<String, Value>, 
@@ -202,7 +367,59 @@ fn add_rectangle(
     h : f64, 
     outline : bool) {
 
-    queue.push(GraphicQueued(GraphicPrim::Rect(x, y, w, h), context));
+    queue.push(GraphicQueued(GraphicPrim::Rect(x, y, w, h, outline), context));
+}
+
+fn add_line(
+    mut queue : RefMut<Vec<GraphicQueued>>, 
+    context : GraphicsContext,
+    x1 : f64, 
+    y1 : f64, 
+    x2 : f64, 
+    y2 : f64) {
+    queue.push(GraphicQueued(GraphicPrim::Line(x1, y1, x2, y2), context));
+}
+
+fn add_circle(
+    mut queue : RefMut<Vec<GraphicQueued>>, 
+    context : GraphicsContext,
+    x : f64, 
+    y : f64, 
+    r : f64, 
+    outline : bool) {
+    queue.push(GraphicQueued(GraphicPrim::Circle(x, y, r, outline), context));
+}
+
+// points is a list of (x y) lists, malformed points are skipped
+fn add_polygon(
+    mut queue : RefMut<Vec<GraphicQueued>>, 
+    context : GraphicsContext,
+    points : &Value, 
+    outline : bool) {
+    let points = match *points {
+        Value::List(ref xs) => xs.iter()
+            .filter_map(|p| match *p {
+                Value::List(ref xy) if xy.len() == 2 => {
+                    match (f64::from_value_ref(&xy[0]), f64::from_value_ref(&xy[1])) {
+                        (Ok(x), Ok(y)) => Some([x, y]),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect::<Vec<[f64; 2]>>(),
+        _ => Vec::new(),
+    };
+    queue.push(GraphicQueued(GraphicPrim::Polygon(points, outline), context));
+}
+
+fn add_sprite(
+    mut queue : RefMut<Vec<GraphicQueued>>, 
+    context : GraphicsContext,
+    x : f64, 
+    y : f64, 
+    name : &str) {
+    queue.push(GraphicQueued(GraphicPrim::Sprite(x, y, name.to_owned()), context));
 }
 
 fn add_text(
@@ -222,3 +439,27 @@ fn set_color(context : &mut GraphicsContext, r : f64, g : f64, b : f64) {
 fn set_alpha(context : &mut GraphicsContext, a : f64) {
     context.color[3] = a as f32;
 }
+
+fn set_font(context : &mut GraphicsContext, font : &str) {
+    context.font = font.to_owned();
+}
+
+fn set_rotation(context : &mut GraphicsContext, rotation : f64) {
+    context.rotation = rotation;
+}
+
+fn set_scale(context : &mut GraphicsContext, x : f64, y : f64) {
+    context.scale = (x, y);
+}
+
+fn set_line_width(context : &mut GraphicsContext, w : f64) {
+    context.line_width = w;
+}
+
+fn set_screen(context : &mut GraphicsContext, screen : bool) {
+    context.screen = screen;
+}
+
+fn set_layer(context : &mut GraphicsContext, layer : i64) {
+    context.layer = layer;
+}
@@ -0,0 +1,309 @@
+// Runs scripts against recorded sequences of events without a window
+//
+// Each json file in the test directory drives one script, eg.
+//  {
+//    "script" : "scripts/console.lisp",
+//    "fields" : { ... },
+//    "player" : [0.0, 0.0],
+//    "steps" : [
+//      { "event" : "press", "key" : "tab" },
+//      { "event" : "tick", "repeat" : 10 },
+//      { "event" : "expect", "state" : "(. state :toggle)", "created" : 0 }
+//    ]
+//  }
+// "fields" is optional and passed to state-init as for world objects.
+// "player" optionally puts a player at that position for scripts to find.
+// Objects scripts create are run too and removed when destroyed, as in
+// the game, but key presses only go to the script under test.
+// Ticks advance timers and tweens by "dt" seconds, 1/60 if not given.
+// An expect step evaluates "state" in the script's interpreter with the
+// object's state bound to state and fails unless it returns true.
+// "created" and "removed" count the CreateObject and RemoveObject
+// commands issued since the previous expect, "objects" and "states" are
+// how many ids get-ids knows and how many objects have state.
+// Any error raised by the script fails the test.
+// `cargo test` runs those in scripts/tests, see tests/scripts.rs
+
+use descriptors::{error_simple, get_array, get_float_or, get_number, get_number_or, get_string, load_json};
+use collision::BBOwnerType;
+use game::{Height, Id, MetaCommand, Pos, Vel, Width, fphys};
+use inventory::Inventory;
+use rustc_serialize::json::{Json, Object};
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, channel};
+use tools::arc_mut;
+use world::IdGen;
+
+use ketos::{Integer, Value};
+
+use super::{DynMap, json_to_lisp};
+use super::world_view::ObjectInfo;
+
+const DEFAULT_DT : fphys = 1.0 / 60.0;
+
+enum Step {
+    Tick(u64, fphys),
+    Press(i32),
+    Release(i32),
+    Expect {
+        state : Option<String>,
+        created : Option<u64>,
+        removed : Option<u64>,
+        objects : Option<u64>,
+        states : Option<u64>,
+    },
+}
+
+struct ScriptTest {
+    script : String,
+    fields : Option<Json>,
+    player : Option<Pos>,
+    steps : Vec<Step>,
+}
+
+// Key codes as piston reports them, single characters map to themselves
+fn parse_key(dname : &str, key : &str) -> Result<i32, Error> {
+    let code = match key {
+        "backspace" => 0x08,
+        "tab" => 0x09,
+        "return" => 0x0D,
+        "escape" => 0x1B,
+        "space" => 0x20,
+        "delete" => 0x7F,
+        _ if key.chars().count() == 1 => key.chars().next().unwrap() as i32,
+        _ => return Err(error_simple(dname, &format!("unknown key {}", key))),
+    };
+    Ok(code)
+}
+
+// A field that may be left out but is an error if it has the wrong type
+fn optional<T>(dname : &str,
+               obj : &Object,
+               field : &str,
+               get : fn(&str, &Object, &str) -> Result<T, Error>) -> Result<Option<T>, Error> {
+    if obj.contains_key(field) {
+        get(dname, obj, field).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_step(dname : &str, obj : &Object) -> Result<Step, Error> {
+    let event = get_string(dname, obj, "event")?;
+    match event.as_str() {
+        "tick" => {
+            let repeat = get_number_or(dname, obj, "repeat", 1)?;
+            let dt = get_float_or(dname, obj, "dt", DEFAULT_DT)?;
+            Ok(Step::Tick(repeat, dt))
+        }
+        "press" => Ok(Step::Press(parse_key(dname, &get_string(dname, obj, "key")?)?)),
+        "release" => Ok(Step::Release(parse_key(dname, &get_string(dname, obj, "key")?)?)),
+        "expect" => {
+            Ok(Step::Expect {
+                state : optional(dname, obj, "state", get_string)?,
+                created : optional(dname, obj, "created", get_number)?,
+                removed : optional(dname, obj, "removed", get_number)?,
+                objects : optional(dname, obj, "objects", get_number)?,
+                states : optional(dname, obj, "states", get_number)?,
+            })
+        }
+        x => Err(error_simple(dname, &format!("unknown event {}", x))),
+    }
+}
+
+fn load_test(path : &Path) -> Result<ScriptTest, Error> {
+    let dname = path.to_str().unwrap_or("script test");
+    let obj = load_json(dname, path)?;
+    let script = get_string(dname, &obj, "script")?;
+    let player = match obj.get("player") {
+        Some(pos) => {
+            let xy = pos.as_array()
+                .and_then(|a| Some((a.get(0)?.as_f64()?, a.get(1)?.as_f64()?)))
+                .ok_or(error_simple(dname, "player must be [x, y]"))?;
+            Some(Pos(xy.0, xy.1))
+        }
+        None => None,
+    };
+    let mut steps = Vec::new();
+    for step_json in get_array(dname, &obj, "steps")? {
+        let step_obj = step_json.as_object()
+            .ok_or(error_simple(dname, "steps must be objects"))?;
+        steps.push(parse_step(dname, step_obj)?);
+    }
+    Ok(ScriptTest {
+        script : script,
+        fields : obj.get("fields").cloned(),
+        player : player,
+        steps : steps,
+    })
+}
+
+// Objects being run, each with its script
+type Objects = Vec<(Id, String)>;
+
+// Apply the CreateObject and RemoveObject commands waiting in rx as the
+// game would, adding to the counts of each
+fn apply_commands(dm : &mut DynMap,
+                  rx : &Receiver<MetaCommand>,
+                  objects : &mut Objects,
+                  counts : &mut (u64, u64)) {
+    for command in rx.try_iter() {
+        match command {
+            MetaCommand::CreateObject(obj) => {
+                counts.0 += 1;
+                dm.add_object_id(obj.name.clone(), obj.id);
+                objects.push((obj.id, format!("scripts/{}.lisp", obj.name)));
+            }
+            MetaCommand::RemoveObject(id) => {
+                counts.1 += 1;
+                dm.remove_object_id(id);
+                objects.retain(|&(x, _)| x != id);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_state(dm : &DynMap, script : &str, id : Id, code : &str) -> Result<(), String> {
+    let interp = dm.interpreters.get(script)
+        .ok_or(format!("{} was never loaded", script))?;
+    let state = dm.state_map.borrow().get(&id).cloned().unwrap_or(Value::Unit);
+    interp.scope().add_named_value("state", state);
+    match interp.run_code(code, None) {
+        Ok(Value::Bool(true)) => Ok(()),
+        Ok(v) => Err(format!("{} returned {}", code, interp.format_value(&v))),
+        Err(e) => {
+            let _ = interp.take_traceback();
+            Err(format!("{} failed: {}", code, interp.format_error(&e)))
+        }
+    }
+}
+
+fn run_test(test : &ScriptTest) -> Result<(), String> {
+    let (tx, rx) = channel();
+    let id_gen = arc_mut(IdGen { current : 0 });
+    let dyn_map = DynMap::construct(id_gen.clone(), tx, arc_mut(Inventory::new()));
+    let mut dm = dyn_map.lock().unwrap();
+
+    let id = IdGen::generate_id(&id_gen);
+    if let Some(ref fields) = test.fields {
+        dm.set_init_args(id, json_to_lisp(fields));
+    }
+    let name = Path::new(&test.script)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("test")
+        .to_owned();
+    dm.add_object_id(name, id);
+    if let Some(pos) = test.player {
+        let player_id = IdGen::generate_id(&id_gen);
+        dm.add_object_id("player".to_owned(), player_id);
+        dm.world_view.borrow_mut().insert(player_id, ObjectInfo {
+            name : "player".to_owned(),
+            pos : pos,
+            vel : Vel(0.0, 0.0),
+            w : Width(0.0),
+            h : Height(0.0),
+            owner_type : BBOwnerType::PLAYER,
+            health : None,
+        });
+    }
+
+    let mut objects : Objects = vec![(id, test.script.clone())];
+    let mut counts = (0, 0);
+    for (i, step) in test.steps.iter().enumerate() {
+        match *step {
+            Step::Tick(repeat, dt) => {
+                for _ in 0..repeat {
+                    //  Objects created this tick first run on the next
+                    let ticking = objects.clone();
+                    for (obj_id, script) in ticking {
+                        //  Destroyed earlier this tick
+                        if !dm.object_names.contains_key(&obj_id) {
+                            continue;
+                        }
+                        dm.advance_animator(&script, obj_id, dt);
+                        dm.advance_schedule(&script, obj_id, dt);
+                        dm.run_event("tick", None, &script, obj_id);
+                        apply_commands(&mut dm, &rx, &mut objects, &mut counts);
+                    }
+                }
+            }
+            Step::Press(key) => {
+                let arg = Value::Integer(Integer::from_i32(key));
+                dm.run_event("press", Some(arg), &test.script, id);
+                apply_commands(&mut dm, &rx, &mut objects, &mut counts);
+            }
+            Step::Release(key) => {
+                let arg = Value::Integer(Integer::from_i32(key));
+                dm.run_event("release", Some(arg), &test.script, id);
+                apply_commands(&mut dm, &rx, &mut objects, &mut counts);
+            }
+            Step::Expect { ref state, created, removed, objects : n_objects, states } => {
+                let fail = |msg : String| format!("step {}: {}", i, msg);
+                let (n_created, n_removed) = counts;
+                counts = (0, 0);
+                let n_ids = dm.object_ids_map.borrow().values().map(|ids| ids.len() as u64).sum();
+                let n_states = dm.state_map.borrow().len() as u64;
+                let checks = [("created", created, n_created),
+                              ("removed", removed, n_removed),
+                              ("objects", n_objects, n_ids),
+                              ("states", states, n_states)];
+                for &(what, expected, got) in &checks {
+                    if let Some(expected) = expected {
+                        if expected != got {
+                            return Err(fail(format!("expected {} {}, got {}", expected, what, got)));
+                        }
+                    }
+                }
+                if let Some(ref code) = *state {
+                    check_state(&dm, &test.script, id, code).map_err(&fail)?;
+                }
+            }
+        }
+        for &(_, ref script) in &objects {
+            if dm.is_disabled(script) {
+                return Err(format!("step {}: {} was disabled", i, script));
+            }
+        }
+        if let Some(e) = dm.script_errors().first() {
+            return Err(format!("step {}: {}", i, e.message));
+        }
+    }
+    Ok(())
+}
+
+// Run every test in dir, returning whether they all passed
+pub fn run_tests(dir : &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Could not read {:?}: {}", dir, e);
+            return false;
+        }
+    };
+    let mut paths = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut failed = 0;
+    for path in &paths {
+        let result = load_test(path)
+            .map_err(|e| e.to_string())
+            .and_then(|test| run_test(&test));
+        match result {
+            Ok(()) => println!("ok   {:?}", path),
+            Err(e) => {
+                println!("FAIL {:?}: {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {} failed", paths.len() - failed, failed);
+    failed == 0
+}
@@ -1,12 +1,14 @@
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use logic::*;
-use game::{Id, InputHandler};
+use game::{Id, InputHandler, ObjMessage, Pos, fphys};
+use physics::Physical;
 use tools::{arc_mut};
 use std::{thread, char};
 use piston::input::*;
 
 use super::DynMap;
+use super::physics::{DynPhysics, owner_type_to_lisp};
 
 use ketos::{Builder, GlobalScope, Scope, Error, Interpreter, Value, Integer};
 
@@ -14,23 +16,64 @@ pub struct DynLogic {
     id: Id,
     dyn_map : Arc<Mutex<DynMap>>,
     logic_name : String,
+    physics : Arc<Mutex<DynPhysics>>,
+    // Where the body is placed once the script declares one
+    spawn_pos : Pos,
+    body_checked : bool,
 }
 
 impl DynLogic {
-    pub fn new(id: Id, dyn_map : Arc<Mutex<DynMap>>, logic_name : String) -> Self {
+    pub fn new(id: Id,
+               dyn_map : Arc<Mutex<DynMap>>,
+               logic_name : String,
+               physics : Arc<Mutex<DynPhysics>>,
+               spawn_pos : Pos) -> Self {
         DynLogic {
             id: id,
             dyn_map: dyn_map,
             logic_name: logic_name,
+            physics: physics,
+            spawn_pos: spawn_pos,
+            body_checked: false,
         }
     }
 }
 
 impl Logical for DynLogic {
-    fn tick(&mut self, _: &LogicUpdateArgs) {
-        {
-            let mut dm = self.dyn_map.lock().unwrap();
-            dm.run_event("tick", None, &self.logic_name, self.id);
+    fn tick(&mut self, args: &LogicUpdateArgs) {
+        let mut dm = self.dyn_map.lock().unwrap();
+
+        for m in args.message_buffer.read_buffer() {
+            match m {
+                ObjMessage::MCollision(c) => {
+                    let event_args = vec![Value::Integer(Integer::from_u32(c.other_id)),
+                                          owner_type_to_lisp(c.other_type)];
+                    dm.run_event_args("on-collision", event_args, &self.logic_name, self.id);
+                }
+                ObjMessage::MScript(from, value) => {
+                    let event_args = vec![Value::Integer(Integer::from_u32(from)), value];
+                    dm.run_event_args("on-message", event_args, &self.logic_name, self.id);
+                }
+                ObjMessage::MTrigger => {
+                    dm.run_event("on-trigger", None, &self.logic_name, self.id);
+                }
+                _ => {}
+            }
+        }
+
+        dm.advance_animator(&self.logic_name, self.id, args.piston.dt as fphys);
+        dm.advance_schedule(&self.logic_name, self.id, args.piston.dt as fphys);
+        dm.run_event("tick", None, &self.logic_name, self.id);
+
+        // The body is asked for once, after state-init has run
+        if !self.body_checked {
+            self.body_checked = true;
+            if let Some(body) = dm.script_body(&self.logic_name, self.id) {
+                let mut p = self.physics.lock().unwrap();
+                if let Err(e) = p.set_body(&body, self.spawn_pos, args.world) {
+                    println!("Bad body in {}: {}", self.logic_name, e);
+                }
+            }
         }
     }
 }
@@ -48,10 +48,10 @@ macro_rules! add_graph_fun {
         let c_tmp = $c.clone();
         $scope.add_value_with_name($name, move |lisp_name| {
             Value::new_foreign_fn(lisp_name, move |_scope, args| {
-                if (args.len() != 3) {
+                if (args.len() != 2) {
                     Err(From::from(ExecError::ArityError{
                         name: Some(lisp_name),
-                        expected: Arity::Exact(5 as u32),
+                        expected: Arity::Exact(2 as u32),
                         found: args.len() as u32,
                     }))
                 }
@@ -74,7 +74,7 @@ macro_rules! add_graph_fun {
                 if (args.len() != 3) {
                     Err(From::from(ExecError::ArityError{
                         name: Some(lisp_name),
-                        expected: Arity::Exact(5 as u32),
+                        expected: Arity::Exact(3 as u32),
                         found: args.len() as u32,
                     }))
                 }
@@ -95,10 +95,10 @@ macro_rules! add_graph_fun {
         let c_tmp = $c.clone();
         $scope.add_value_with_name($name, move |lisp_name| {
             Value::new_foreign_fn(lisp_name, move |_scope, args| {
-                if (args.len() != 3) {
+                if (args.len() != 4) {
                     Err(From::from(ExecError::ArityError{
                         name: Some(lisp_name),
-                        expected: Arity::Exact(5 as u32),
+                        expected: Arity::Exact(4 as u32),
                         found: args.len() as u32,
                     }))
                 }
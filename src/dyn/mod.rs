@@ -1,16 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use notify::{Watcher, RecursiveMode, watcher, DebouncedEvent, INotifyWatcher};
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::time::Duration;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use game::{Id, InputHandler};
 use tools::{arc_mut};
 use std::{thread, char};
 use piston::input::*;
 use opengl_graphics::GlGraphics;
-use draw::ViewTransform;
+use draw::{CameraPriority, Rectangle, ViewFollower, ViewArena, ViewTransform};
+use shaders::NoisyShader;
 use self::graphics::GraphicsContext;
 use self::graphics::ResourceContext;
 use self::graphics::GraphicPrim;
@@ -18,31 +19,85 @@ use self::graphics::GraphicQueued;
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::cell::RefMut;
-use game::{MetaCommand, GameObj, fphys};
-use world::IdGen;
-use physics::PhysNone;
+use game::{MetaCommand, GameObj, ObjMessage, Force, Pos, Vel, Width, Height, TriggerId, fphys};
+use world::{IdGen, World};
+use collision::{BBOwnerType, BoundingBox};
+use inventory::Inventory;
+use rustc_serialize::json::Json;
 
-use ketos::{Builder, GlobalScope, Scope, Error, Interpreter, Value, Integer, ExecError, Arity, FromValueRef};
+use ketos::{Builder, GlobalScope, Scope, Error, Interpreter, Value, Integer, ExecError, Arity, FromValue, FromValueRef};
 
 #[macro_use]
 mod macros;
 pub mod logic;
 pub mod graphics;
+pub mod console;
+pub mod physics;
+pub mod sandbox;
+pub mod harness;
+pub mod schedule;
+pub mod world_view;
+pub mod errors;
+pub mod animation;
 
 use self::logic::DynLogic;
 use self::graphics::DynGraphics;
+use self::physics::{Body, DynPhysics, parse_owner_type};
+use self::world_view::{ObjectInfo, WorldView};
+use self::errors::{ScriptError, ScriptErrors};
+use self::schedule::{EASES, Ease, Schedule, SequenceStep, handle_to_lisp};
+use self::sandbox::{SandboxModuleLoader, lib_module_name, sandbox_config};
+use self::animation::{AnimatedCache, Animators, ScriptAnimator, load_animated, param_from_value, reload_animated};
+use animator::Animator;
 
 
+const DESCRIPTOR_PATH : &'static str = "descriptors";
+// Animations only scripts use, see animation.rs
+const ANIMATED_PATH : &'static str = "descriptors/anim";
+
+// Lerp weight for script cameras that don't give one
+const SCRIPT_CAMERA_WEIGHT : fphys = 20.0;
+
 pub struct DynMap {
     pub interpreters: HashMap<String, Interpreter>,
-    pub state_map: RefCell<HashMap<Id, Value>>,
+    // Shared with the lisp functions so they always see live objects
+    pub state_map: Rc<RefCell<HashMap<Id, Value>>>,
     pub resource_context : Arc<ResourceContext>,
     watcher : INotifyWatcher,
     graphics_variables : HashMap<String, Value>,
     rx : Receiver<DebouncedEvent>,
-    object_ids_map : RefCell<HashMap<String, Vec<Id>>>,
+    object_ids_map : Rc<RefCell<HashMap<String, Vec<Id>>>>,
+    // Reverse of object_ids_map so removal doesn't need a search
+    object_names : HashMap<Id, String>,
+    // Script file each object with state is running
+    object_scripts : HashMap<Id, String>,
+    // Arguments for state-init, taken when the object first runs
+    init_args : Rc<RefCell<HashMap<Id, Value>>>,
+    // What scripts can see of every object, rebuilt each tick
+    world_view : Rc<RefCell<WorldView>>,
+    // Timers, tweens and sequences, kept beside the state so they
+    // survive reloads and go when the object does
+    schedules : Rc<RefCell<HashMap<Id, Schedule>>>,
+    // Animators scripts have attached, see animation.rs
+    animators : Animators,
+    animated : AnimatedCache,
+    // Objects whose script has set a camera, so only they need one removed
+    cameras : Rc<RefCell<HashSet<Id>>>,
+    // Primitives from every script's draw, waiting for flush_draw
+    draw_queue : Vec<GraphicQueued>,
     metabuffer_tx : Sender<MetaCommand>,
     id_gen : Arc<Mutex<IdGen>>,
+    inventory : Arc<Mutex<Inventory>>,
+    // Scripts that broke the sandbox limits or failed to compile,
+    // cleared when reloaded
+    disabled : RefCell<HashSet<String>>,
+    errors : Rc<RefCell<ScriptErrors>>,
+    // Changed files under descriptors/ waiting for the game to reload them
+    descriptor_changes : Vec<PathBuf>,
+    // Used by the console when no script is targeted
+    console_interp : Option<Interpreter>,
+    // Library modules each script's interpreter has loaded
+    script_deps : RefCell<HashMap<String, Rc<RefCell<HashSet<String>>>>>,
 
     // This is really ugly but we need a self reference
     // if treated badly this could easily lead to deadlocks
@@ -63,17 +118,64 @@ fn chr(x : u32) -> Result<char, Error> {
     }
 }
 
-pub fn make_dyn_obj(id : Id, dyn_map : &Arc<Mutex<DynMap>>, resource_context : &Arc<ResourceContext>, logic_name : &str) -> (GameObj, Arc<Mutex<InputHandler>>) {
+pub fn make_dyn_obj(id : Id, dyn_map : &Arc<Mutex<DynMap>>, resource_context : &Arc<ResourceContext>, logic_name : &str, pos : Pos) -> (GameObj, Arc<Mutex<InputHandler>>) {
     let logic_filename = format!("scripts/{}.lisp", logic_name);
-    let dl = DynLogic::new(id, dyn_map.clone(), logic_filename.clone());
-    let am_dl = arc_mut(dl);
-    let dg = DynGraphics::new(id, dyn_map.clone(), logic_filename, resource_context.clone());
+    let dg = DynGraphics::new(id, dyn_map.clone(), logic_filename.clone(), resource_context.clone());
     let am_dg = arc_mut(dg);
-    let phs = arc_mut(PhysNone {id: id});
+    let phs = arc_mut(DynPhysics::new(id, am_dg.clone()));
+    let dl = DynLogic::new(id, dyn_map.clone(), logic_filename, phs.clone(), pos);
+    let am_dl = arc_mut(dl);
     let gobj = GameObj::new(id, logic_name.to_owned(), am_dg, phs, am_dl.clone());
     (gobj, am_dl)
 }
 
+fn set_me(scope : &GlobalScope, id : Id) {
+    scope.add_named_value("me", Value::Integer(Integer::from_u32(id)));
+}
+
+// Convert json into lisp values so it can be handed to scripts
+// Objects become association lists of (key value), read with (field ...)
+pub fn json_to_lisp(json : &Json) -> Value {
+    match *json {
+        Json::I64(x) => Value::Integer(Integer::from_i64(x)),
+        Json::U64(x) => Value::Integer(Integer::from_u64(x)),
+        Json::F64(x) => Value::Float(x),
+        Json::String(ref x) => Value::from(x.as_str()),
+        Json::Boolean(x) => Value::Bool(x),
+        Json::Array(ref xs) => Value::from(xs.iter().map(json_to_lisp).collect::<Vec<Value>>()),
+        Json::Object(ref obj) => {
+            let pairs = obj.iter()
+                .map(|(k, v)| Value::from(vec![Value::from(k.as_str()), json_to_lisp(v)]))
+                .collect::<Vec<Value>>();
+            Value::from(pairs)
+        }
+        Json::Null => Value::Unit,
+    }
+}
+
+// Id of the object whose script is currently running
+fn sender_id(scope : &Scope) -> Id {
+    scope.get_named_value("me")
+        .and_then(|v| FromValue::from_value(v).ok())
+        .unwrap_or(0)
+}
+
+// Messages are read by another interpreter with its own names, so only
+// numbers, strings and lists of them can be sent
+fn check_message(value : &Value) -> Result<(), ExecError> {
+    match *value {
+        Value::Unit | Value::Bool(_) | Value::Integer(_) | Value::Ratio(_) |
+        Value::Float(_) | Value::Char(_) | Value::String(_) => Ok(()),
+        Value::List(ref items) => {
+            for item in items.iter() {
+                check_message(item)?;
+            }
+            Ok(())
+        }
+        ref other => Err(ExecError::expected("number, string or list of them", other)),
+    }
+}
+
 fn display_error(interp: &Interpreter, e: &Error) {
     if let Some(trace) = interp.take_traceback() {
         interp.display_trace(&trace);
@@ -81,34 +183,50 @@ fn display_error(interp: &Interpreter, e: &Error) {
     interp.display_error(e);
 }
 impl DynMap {
-    pub fn new(id_gen : Arc<Mutex<IdGen>>, metabuffer_tx : Sender<MetaCommand>) -> Self {
+    pub fn new(id_gen : Arc<Mutex<IdGen>>,
+               metabuffer_tx : Sender<MetaCommand>,
+               inventory : Arc<Mutex<Inventory>>) -> Self {
         let (tx, rx) = channel();       
         let mut watcher = watcher(tx, Duration::from_millis(1)).unwrap();
         let scr_path = "scripts";
         watcher.watch(&scr_path, RecursiveMode::Recursive).unwrap();
+        // Descriptor changes are passed on to the game, see take_descriptor_changes
+        watcher.watch(&DESCRIPTOR_PATH, RecursiveMode::Recursive).unwrap();
 
         let mut m = DynMap {
             interpreters: HashMap::new(),
-            state_map: RefCell::new(HashMap::new()),
+            state_map: Rc::new(RefCell::new(HashMap::new())),
             watcher: watcher,
             rx: rx,
             resource_context : Arc::new(ResourceContext::new()),
             graphics_variables : HashMap::new(),
             //default_scope : default_scope,
-            object_ids_map : RefCell::new(HashMap::new()),
+            object_ids_map : Rc::new(RefCell::new(HashMap::new())),
+            object_names : HashMap::new(),
+            object_scripts : HashMap::new(),
+            init_args : Rc::new(RefCell::new(HashMap::new())),
+            world_view : Rc::new(RefCell::new(WorldView::new())),
+            schedules : Rc::new(RefCell::new(HashMap::new())),
+            animators : Rc::new(RefCell::new(HashMap::new())),
+            cameras : Rc::new(RefCell::new(HashSet::new())),
+            animated : Rc::new(RefCell::new(HashMap::new())),
+            draw_queue : Vec::new(),
             metabuffer_tx,
             id_gen,
+            inventory,
+            disabled : RefCell::new(HashSet::new()),
+            errors : Rc::new(RefCell::new(ScriptErrors::new())),
+            descriptor_changes : Vec::new(),
+            console_interp : None,
+            script_deps : RefCell::new(HashMap::new()),
             self_reference : None,
         };
 
         m 
     }
 
-    pub fn update_obj_state(&mut self, id : &Id, state : Value) {
-        self.state_map.borrow_mut().insert(*id, state);
-    }
-
     pub fn add_object_id(&mut self, object_name : String, id : Id) {
+        self.object_names.insert(id, object_name.clone());
         let mut oim = self.object_ids_map.borrow_mut();
         let mut list = oim.entry(object_name)
                       .or_insert(Vec::new());
@@ -116,12 +234,43 @@ impl DynMap {
     }
 
     pub fn remove_object_id(&mut self, id : Id) {
-        // TODO
-        // DO THIS
+        // Let the script clean up before its state is dropped
+        let script = self.object_scripts.remove(&id);
+        let state = self.state_map.borrow_mut().remove(&id);
+        self.schedules.borrow_mut().remove(&id);
+        self.animators.borrow_mut().remove(&id);
+        // Drop any camera the object set up
+        if self.cameras.borrow_mut().remove(&id) {
+            let _ = self.metabuffer_tx.send(MetaCommand::RemoveCamera(id));
+        }
+        if let (Some(name), Some(state)) = (script, state) {
+            if let Some(interp) = self.interpreters.get(&name) {
+                set_me(interp.scope(), id);
+                if let Err(e) = interp.call("on-destroy", vec![state]) {
+                    self.script_error(&name, interp, &e);
+                }
+            }
+        }
+
+        if let Some(name) = self.object_names.remove(&id) {
+            let mut oim = self.object_ids_map.borrow_mut();
+            let empty = match oim.get_mut(&name) {
+                Some(ids) => {
+                    ids.retain(|&x| x != id);
+                    ids.is_empty()
+                }
+                None => false,
+            };
+            if empty {
+                oim.remove(&name);
+            }
+        }
     }
 
-    pub fn construct(id_gen : Arc<Mutex<IdGen>>, mb : Sender<MetaCommand>) -> Arc<Mutex<Self>> {
-        let d = Self::new(id_gen, mb);
+    pub fn construct(id_gen : Arc<Mutex<IdGen>>,
+                     mb : Sender<MetaCommand>,
+                     inventory : Arc<Mutex<Inventory>>) -> Arc<Mutex<Self>> {
+        let d = Self::new(id_gen, mb, inventory);
         let am = arc_mut(d);
 
         {
@@ -133,13 +282,23 @@ impl DynMap {
     }
 
     pub fn update(&mut self) {
-        for x in self.rx.try_iter() { 
+        let events : Vec<DebouncedEvent> = self.rx.try_iter().collect();
+        for x in events { 
             match x {
-                DebouncedEvent::Write(_) => {
-                    // For now just nuke everything
-                    println!("AFOUND UPDATE");
-                    self.interpreters = HashMap::new();
-                    self.state_map = RefCell::new(HashMap::new());
+                // Some editors save by writing a new file over the old one
+                DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => {
+                    if path.components().any(|c| c.as_os_str() == DESCRIPTOR_PATH) {
+                        // Scripts' animations are reloaded here, the rest by the game
+                        if reload_animated(&self.animated, &self.animators, &path) ||
+                           path.parent().map_or(false, |p| p.ends_with(ANIMATED_PATH)) {
+                            continue;
+                        }
+                        if !self.descriptor_changes.contains(&path) {
+                            self.descriptor_changes.push(path);
+                        }
+                    } else {
+                        self.reload_scripts(&path);
+                    }
                 },
                 e => {
                     println!("{:?}", e);
@@ -149,6 +308,99 @@ impl DynMap {
 
     }
 
+    pub fn take_descriptor_changes(&mut self) -> Vec<PathBuf> {
+        self.descriptor_changes.drain(..).collect()
+    }
+
+    // Recompile any loaded script matching path, or using the library
+    // module at path, and migrate the state of every object running it.
+    // On a compile error the old version keeps running.
+    fn reload_scripts(&mut self, path : &Path) {
+        let module = lib_module_name(path);
+        let names : Vec<String> = {
+            let deps = self.script_deps.borrow();
+            // Scripts that never compiled aren't in interpreters
+            let failed = self.disabled.borrow();
+            self.interpreters.keys()
+                .chain(failed.iter().filter(|name| !self.interpreters.contains_key(*name)))
+                .filter(|name| {
+                    path.ends_with(Path::new(name.as_str())) ||
+                    module.as_ref().map_or(false, |m| {
+                        deps.get(name.as_str()).map_or(false, |d| d.borrow().contains(m))
+                    })
+                })
+                .cloned()
+                .collect()
+        };
+
+        for name in names {
+            let ids : Vec<Id> = self.object_scripts.iter()
+                .filter(|&(_, script)| *script == name)
+                .map(|(id, _)| *id)
+                .collect();
+            let scope_id = ids.first().cloned().unwrap_or(0);
+
+            // States hold names interned by the old interpreter
+            let interp = match self.new_interpreter_from(scope_id, &name, self.interpreters.get(&name)) {
+                Some(x) => x,
+                None => {
+                    println!("Keeping previous version of {}", name);
+                    continue;
+                }
+            };
+
+            for id in ids {
+                let old = self.state_map.borrow().get(&id).cloned();
+                if let Some(state) = old {
+                    set_me(interp.scope(), id);
+                    let migrated = match interp.call("migrate-state", vec![state.clone()]) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            self.script_error(&name, &interp, &e);
+                            state
+                        }
+                    };
+                    self.state_map.borrow_mut().insert(id, migrated);
+                }
+            }
+
+            println!("Reloaded {}", name);
+            self.disabled.borrow_mut().remove(&name);
+            self.errors.borrow_mut().clear_script(&name);
+            self.interpreters.insert(name, interp);
+        }
+    }
+
+    // Evaluate code typed into the console, either in the interpreter of
+    // the script at target or a shared global one
+    pub fn eval_console(&mut self, target : Option<&str>, code : &str) -> Result<String, String> {
+        let interp = match target {
+            Some(name) => {
+                if !self.interpreters.contains_key(name) {
+                    match self.new_interpreter(0, name) {
+                        Some(x) => { self.interpreters.insert(name.to_owned(), x); }
+                        None => { return Err(format!("Could not load {}", name)); }
+                    }
+                }
+                self.interpreters.get(name).unwrap()
+            }
+            None => {
+                if self.console_interp.is_none() {
+                    self.console_interp = Some(self.new_bare_interpreter(0, "console"));
+                }
+                self.console_interp.as_ref().unwrap()
+            }
+        };
+
+        match interp.run_code(code, None) {
+            Ok(v) => Ok(interp.format_value(&v)),
+            Err(e) => {
+                let _ = interp.take_traceback();
+                Err(interp.format_error(&e))
+            }
+        }
+    }
+
     pub fn update_graphics_variables(&mut self, args : &RenderArgs, ctx : &mut GlGraphics, vt : &ViewTransform) {
         graphics::get_graphics_variables(&mut self.graphics_variables, args, ctx, vt);
     }
@@ -158,6 +410,18 @@ impl DynMap {
                      arg : Option<Value>,
                      name : &str,
                      id: Id) {
+        self.run_event_args(event, arg.into_iter().collect(), name, id);
+    }
+
+    // Calls (event state args...) and stores the returned state
+    pub fn run_event_args(&mut self,
+                          event : &str,
+                          args : Vec<Value>,
+                          name : &str,
+                          id: Id) {
+        if self.is_disabled(name) {
+            return;
+        }
 
         //self.interpreters.entry(name.to_owned())
             //.or_insert_with(|| self.new_interpreter(name));
@@ -170,35 +434,32 @@ impl DynMap {
             let interp;
             match self.new_interpreter(id, name) {
                 Some(x) => {interp = x}
-                None => {return ()}
+                None => {
+                    // Don't retry every frame, a reload will try again
+                    self.disabled.borrow_mut().insert(name.to_owned());
+                    return ()
+                }
             }
             self.interpreters.insert(name.to_owned(), interp);
     
         }
 
+        if !self.object_scripts.contains_key(&id) {
+            self.object_scripts.insert(id, name.to_owned());
+        }
         let interp = self.interpreters.get(name).unwrap(); // pretty ugly but otherwise
 
-        let state;
-        {
-            let mut mut_sm = self.state_map.borrow_mut();
-            // I think this is slightly less ugly
-            // we can't do this in the prev as it require mut / imm self references
-            mut_sm.entry(id)
-                .or_insert_with(|| interp.call("state-init", vec![]).unwrap());
-                //.or_insert_with(|| interp.get_value("state-init").unwrap());
-            state = mut_sm.get(&id).unwrap().clone();
-        }
+        set_me(interp.scope(), id);
+        let state = self.get_or_init_state(name, interp, id);
         
-        let argvec = match arg {
-            Some(x) => vec![state, x],
-            None => vec![state],
-        };
+        let mut argvec = vec![state];
+        argvec.extend(args);
 
 
         let v = match interp.call(event, argvec) {
             Ok(x) => x,
             Err(e) => {
-                display_error(&interp, &e);
+                self.script_error(name, &interp, &e);
                 Value::Unit
             }
         };
@@ -210,39 +471,40 @@ impl DynMap {
     }
 
 
+    // Queues up the object's primitives, they are drawn by flush_draw
     pub fn run_draw(&mut self,
                     id : Id,
-                    name : &str,
-                    rargs : &RenderArgs,
-                    ctx : &mut GlGraphics,
-                    vt : &ViewTransform) {
+                    name : &str) {
+        if self.is_disabled(name) {
+            return;
+        }
 
         if !self.interpreters.contains_key(name) {
             let interp;
             match self.new_interpreter(id, name) {
                 Some(x) => {interp = x}
-                None => {return ()}
+                None => {
+                    // Don't retry every frame, a reload will try again
+                    self.disabled.borrow_mut().insert(name.to_owned());
+                    return ()
+                }
             }
             self.interpreters.insert(name.to_owned(), interp);
     
         }
+        if !self.object_scripts.contains_key(&id) {
+            self.object_scripts.insert(id, name.to_owned());
+        }
         let interp = self.interpreters.get(name).unwrap(); // pretty ugly but otherwise
 
-        let state;
-        {
-            let mut mut_sm = self.state_map.borrow_mut();
-            mut_sm.entry(id)
-                .or_insert_with(|| interp.call("state-init", vec![]).unwrap());
-                //.or_insert_with(|| interp.get_value("state-init").unwrap());
-            state = mut_sm.get(&id).unwrap().clone();
-        }
+        let state = self.get_or_init_state(name, interp, id);
         
         let argvec = vec![state];
 
         let c = Rc::new(RefCell::new(Vec::new()));
 
-        self.add_logic_funs(id, interp.scope());
-        graphics::add_graphic_funs(interp.scope(), &c);
+        self.add_logic_funs(id, name, interp.scope());
+        graphics::add_graphic_funs(interp.scope(), &c, &self.animators);
 
         for var in self.graphics_variables.keys() {
             let value = self.graphics_variables.get(var).unwrap();
@@ -253,17 +515,163 @@ impl DynMap {
         let v = match interp.call("draw", argvec) {
             Ok(x) => x,
             Err(e) => {
-                display_error(&interp, &e);
+                self.script_error(name, &interp, &e);
                 Value::Unit
             }
         };
 
-        for prim in c.borrow().iter() {
+        self.draw_queue.extend(c.borrow_mut().drain(..));
+    }
+
+    // Draw everything scripts queued this frame, lowest layer first
+    // Sort is stable so draw order within a layer is kept
+    // Returns how many primitives were drawn, each with a draw of its own
+    pub fn flush_draw(&mut self,
+                      rargs : &RenderArgs,
+                      ctx : &mut GlGraphics,
+                      vt : &ViewTransform,
+                      shader : &NoisyShader) -> usize {
+        self.draw_queue.sort_by_key(|q| q.1.layer);
+        let count = self.draw_queue.len();
+        for prim in self.draw_queue.drain(..) {
+            match prim.0 {
+                GraphicPrim::Sprite(..) | GraphicPrim::Animation(..) => shader.set_textured(ctx),
+                _ => shader.set_colored(ctx),
+            }
             prim.draw(&self.resource_context, rargs, ctx, vt);
         }
+        shader.set_colored(ctx);
+        count
+    }
+
+    // Run any timer, tween or sequence events due after dt seconds
+    pub fn advance_schedule(&mut self, name : &str, id : Id, dt : fphys) {
+        let calls = {
+            let mut schedules = self.schedules.borrow_mut();
+            let calls = match schedules.get_mut(&id) {
+                Some(schedule) => schedule.advance(dt),
+                None => return,
+            };
+            if schedules.get(&id).map_or(false, |s| s.is_empty()) {
+                schedules.remove(&id);
+            }
+            calls
+        };
+        // Events may schedule more, so the map isn't borrowed while they run
+        for (event, args) in calls {
+            self.run_event_args(&event, args, name, id);
+        }
+    }
+
+    // Step the object's animator, calling (anim-event state state-name event)
+    // for each event it reaches
+    pub fn advance_animator(&mut self, name : &str, id : Id, dt : fphys) {
+        let (state, events) = {
+            let mut animators = self.animators.borrow_mut();
+            match animators.get_mut(&id) {
+                Some(a) => {
+                    let events = a.animator.update(dt, &a.descr.animations);
+                    (a.animator.state().to_owned(), events)
+                }
+                None => return,
+            }
+        };
+        for event in events {
+            let args = vec![Value::from(state.as_str()), Value::from(event.as_str())];
+            self.run_event_args("anim-event", args, name, id);
+        }
+    }
+
+    pub fn set_init_args(&mut self, id : Id, args : Value) {
+        self.init_args.borrow_mut().insert(id, args);
+    }
+
+    pub fn update_world_view(&mut self, objs : &[GameObj], world : &World) {
+        self.world_view.borrow_mut().update(objs, world);
+    }
+
+    // Ask a script what physics body it wants, None for no body
+    pub fn script_body(&mut self, name : &str, id : Id) -> Option<Body> {
+        let interp = match self.interpreters.get(name) {
+            Some(x) => x,
+            None => return None,
+        };
+        let state = match self.state_map.borrow().get(&id) {
+            Some(x) => x.clone(),
+            None => return None,
+        };
+        set_me(interp.scope(), id);
+        match interp.call("body", vec![state]) {
+            Ok(Value::Unit) => None,
+            Ok(v) => match Body::from_value(v) {
+                Ok(body) => Some(body),
+                Err(e) => {
+                    self.script_error(name, interp, &Error::from(e));
+                    None
+                }
+            },
+            Err(e) => {
+                self.script_error(name, interp, &e);
+                None
+            }
+        }
+    }
+
+    // Report an error from a script, disabling it if it went over its limits
+    // Only the first of each distinct error is printed
+    fn script_error(&self, name : &str, interp : &Interpreter, e : &Error) {
+        let message = interp.format_error(e);
+        if self.errors.borrow_mut().report(name, message) {
+            display_error(interp, e);
+        } else {
+            let _ = interp.take_traceback();
+        }
+        if let Error::RestrictError(ref r) = *e {
+            println!("Disabling {}: {}", name, r);
+            self.disabled.borrow_mut().insert(name.to_owned());
+        }
+    }
+
+    pub fn is_disabled(&self, name : &str) -> bool {
+        self.disabled.borrow().contains(name)
+    }
+
+    // Every error scripts have raised, for automated runs to check
+    pub fn script_errors(&self) -> Vec<ScriptError> {
+        self.errors.borrow().all().to_vec()
+    }
+
+    // Errors that haven't been dismissed from the overlay
+    pub fn visible_script_errors(&self) -> Vec<ScriptError> {
+        self.errors.borrow().visible()
+    }
+
+    pub fn dismiss_script_errors(&self) {
+        self.errors.borrow_mut().dismiss();
     }
 
-    fn add_logic_funs(&self, id : Id, scope : &GlobalScope) {
+    // state-init may call get, so the state map can't be borrowed while it runs
+    fn get_or_init_state(&self, name : &str, interp : &Interpreter, id : Id) -> Value {
+        let existing = self.state_map.borrow().get(&id).cloned();
+        match existing {
+            Some(state) => state,
+            None => {
+                let args = self.init_args.borrow_mut().remove(&id).unwrap_or(Value::Unit);
+                let state = match interp.call("state-init", vec![args]) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        self.script_error(name, interp, &e);
+                        Value::Unit
+                    }
+                };
+                self.state_map.borrow_mut().insert(id, state.clone());
+                state
+            }
+        }
+    }
+
+    // name is the script the functions are for, problems are reported against it
+    fn add_logic_funs(&self, id : Id, name : &str, scope : &GlobalScope) {
         let oim = self.object_ids_map.clone();
         scope.add_value_with_name("get-ids", move |lisp_name| {
             Value::new_foreign_fn(lisp_name, move |_scope, args| {
@@ -313,7 +721,7 @@ impl DynMap {
         //scope.add_value_with_name("id", move |lisp_name| {
             //Value::from(id)
         //});
-        scope.add_named_value("me", Value::Integer(Integer::from_u32(id)));
+        set_me(scope, id);
         
 
 
@@ -322,23 +730,28 @@ impl DynMap {
             let self_reference : Arc<Mutex<DynMap>> = self.self_reference.clone().unwrap();
             let resource_context = self.resource_context.clone();
             let metabuffer_tx = self.metabuffer_tx.clone();
+            let init_args = self.init_args.clone();
+            // (create x y script [args]) returns the new object's id
             scope.add_value_with_name("create", move |lisp_name| {
                 Value::new_foreign_fn(lisp_name, move |_scope, args| {
-                    if (args.len() == 3) {
+                    if args.len() == 3 || args.len() == 4 {
                         let x : fphys = FromValueRef::from_value_ref(&args[0])?;
                         let y : fphys = FromValueRef::from_value_ref(&args[1])?;
                         let script : &str = FromValueRef::from_value_ref(&args[2])?;
                         let id = IdGen::generate_id(&id_gen);
+                        if args.len() == 4 {
+                            init_args.borrow_mut().insert(id, args[3].clone());
+                        }
                         //let g = GameObj {}
-                        let (gobj, _) = make_dyn_obj(id, &self_reference, &resource_context, script);
+                        let (gobj, _) = make_dyn_obj(id, &self_reference, &resource_context, script, Pos(x, y));
                         //println!("SENDING {}", script);
                         metabuffer_tx.send(MetaCommand::CreateObject(gobj)).unwrap();
-                        Ok(Value::Unit)
+                        Ok(Value::Integer(Integer::from_u32(id)))
                     }
                     else {
                         Err(From::from(ExecError::ArityError{
                             name: Some(lisp_name),
-                            expected: Arity::Exact(1 as u32),
+                            expected: Arity::Range(3, 4),
                             found: args.len() as u32,
                         }))
                     }
@@ -364,34 +777,778 @@ impl DynMap {
                 })
             });
         }
+        {
+            // (send id value) calls on-message in the target's script
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            scope.add_value_with_name("send", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 2 {
+                        let to : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        check_message(&args[1])?;
+                        let from = sender_id(scope);
+                        let msg = ObjMessage::MScript(from, args[1].clone());
+                        metabuffer_tx.send(MetaCommand::MessageObject(to, msg)).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(2 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (broadcast name value) sends to every object called name
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            let oim = self.object_ids_map.clone();
+            scope.add_value_with_name("broadcast", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 2 {
+                        let obj_name : &str = FromValueRef::from_value_ref(&args[0])?;
+                        check_message(&args[1])?;
+                        let from = sender_id(scope);
+                        if let Some(ids) = oim.borrow().get(obj_name) {
+                            for &to in ids {
+                                let msg = ObjMessage::MScript(from, args[1].clone());
+                                metabuffer_tx.send(MetaCommand::MessageObject(to, msg)).unwrap();
+                            }
+                        }
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(2 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (trigger n) fires trigger id n, same as a trigger object
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            scope.add_value_with_name("trigger", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 1 {
+                        let trigger_id : TriggerId = FromValueRef::from_value_ref(&args[0])?;
+                        metabuffer_tx.send(MetaCommand::Trigger(trigger_id)).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (after secs event [arg]) and (every secs event [arg]) run
+            // (event state [arg]) once or repeatedly, see schedule.rs
+            for &(fun_name, repeat) in &[("after", false), ("every", true)] {
+                let schedules = self.schedules.clone();
+                scope.add_value_with_name(fun_name, move |lisp_name| {
+                    Value::new_foreign_fn(lisp_name, move |scope, args| {
+                        if args.len() == 2 || args.len() == 3 {
+                            let secs : fphys = FromValueRef::from_value_ref(&args[0])?;
+                            let event : &str = FromValueRef::from_value_ref(&args[1])?;
+                            let arg = args.get(2).cloned();
+                            let mut schedules = schedules.borrow_mut();
+                            let schedule = schedules.entry(sender_id(scope))
+                                .or_insert_with(Schedule::new);
+                            let handle = if repeat {
+                                schedule.every(secs, event.to_owned(), arg)
+                            } else {
+                                schedule.after(secs, event.to_owned(), arg)
+                            };
+                            Ok(handle_to_lisp(handle))
+                        }
+                        else {
+                            Err(From::from(ExecError::ArityError{
+                                name: Some(lisp_name),
+                                expected: Arity::Range(2, 3),
+                                found: args.len() as u32,
+                            }))
+                        }
+                    })
+                });
+            }
+        }
+        for &(ease_name, ease) in EASES.iter() {
+            // (ease-out-quad t) and the rest, the same curves tweens use
+            scope.add_value_with_name(ease_name, move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 1 {
+                        let t : fphys = FromValueRef::from_value_ref(&args[0])?;
+                        Ok(Value::Float(ease.apply(t)))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (tween event from to secs [ease]) calls (event state value)
+            // every tick until value reaches to
+            let schedules = self.schedules.clone();
+            let errors = self.errors.clone();
+            let script = name.to_owned();
+            scope.add_value_with_name("tween", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 4 || args.len() == 5 {
+                        let event : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let from : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let to : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        let secs : fphys = FromValueRef::from_value_ref(&args[3])?;
+                        let ease = match args.get(4) {
+                            Some(v) => {
+                                let ease_name : &str = FromValueRef::from_value_ref(v)?;
+                                Ease::parse(ease_name).unwrap_or_else(|| {
+                                    errors.borrow_mut().report(&script,
+                                        format!("Unknown ease {}, using linear", ease_name));
+                                    Ease::Linear
+                                })
+                            }
+                            None => Ease::Linear,
+                        };
+                        let mut schedules = schedules.borrow_mut();
+                        let handle = schedules.entry(sender_id(scope))
+                            .or_insert_with(Schedule::new)
+                            .tween(event.to_owned(), from, to, secs, ease);
+                        Ok(handle_to_lisp(handle))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Range(4, 5),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (sequence steps) where steps are event names or (wait secs)
+            let schedules = self.schedules.clone();
+            scope.add_value_with_name("sequence", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 1 {
+                        let mut steps = Vec::new();
+                        if let Value::List(ref xs) = args[0] {
+                            for x in xs.iter() {
+                                steps.push(match *x {
+                                    Value::String(ref event) => SequenceStep::Call(event[..].to_owned()),
+                                    Value::Float(secs) => SequenceStep::Wait(secs),
+                                    Value::Integer(ref secs) => {
+                                        SequenceStep::Wait(secs.to_f64().unwrap_or(0.0))
+                                    }
+                                    ref other => {
+                                        return Err(From::from(ExecError::expected("string or number", other)));
+                                    }
+                                });
+                            }
+                        }
+                        let mut schedules = schedules.borrow_mut();
+                        let handle = schedules.entry(sender_id(scope))
+                            .or_insert_with(Schedule::new)
+                            .sequence(steps);
+                        Ok(handle_to_lisp(handle))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            let schedules = self.schedules.clone();
+            scope.add_value_with_name("cancel", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 1 {
+                        let handle : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        if let Some(schedule) = schedules.borrow_mut().get_mut(&sender_id(scope)) {
+                            schedule.cancel(handle);
+                        }
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (animator path) attaches the animations and animator in the
+            // descriptor at path, returning false if it couldn't be loaded
+            let animators = self.animators.clone();
+            let animated = self.animated.clone();
+            let errors = self.errors.clone();
+            let script = name.to_owned();
+            scope.add_value_with_name("animator", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 1 {
+                        let path : &str = FromValueRef::from_value_ref(&args[0])?;
+                        Ok(Value::Bool(match load_animated(&animated, path) {
+                            Ok(descr) => {
+                                let animator = Animator::new(descr.animator.clone());
+                                animators.borrow_mut().insert(sender_id(scope), ScriptAnimator {
+                                    path : path.to_owned(),
+                                    descr : descr,
+                                    animator : animator,
+                                });
+                                true
+                            }
+                            Err(e) => {
+                                errors.borrow_mut().report(&script,
+                                    format!("Could not load animator {}: {}", path, e));
+                                false
+                            }
+                        }))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (anim-set param value) where value is a number or bool
+            let animators = self.animators.clone();
+            scope.add_value_with_name("anim-set", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 2 {
+                        let param : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let value = param_from_value(&args[1])?;
+                        if let Some(a) = animators.borrow_mut().get_mut(&sender_id(scope)) {
+                            a.animator.set(param, value);
+                        }
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(2 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (anim-state) gives the current state's name, or () without an animator
+            let animators = self.animators.clone();
+            scope.add_value_with_name("anim-state", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 0 {
+                        Ok(match animators.borrow().get(&sender_id(scope)) {
+                            Some(a) => Value::from(a.animator.state()),
+                            None => Value::Unit,
+                        })
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(0 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (anim-play state [blend]) switches state regardless of the
+            // transitions, false if there is no such state
+            let animators = self.animators.clone();
+            scope.add_value_with_name("anim-play", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 1 || args.len() == 2 {
+                        let state : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let blend : fphys = match args.get(1) {
+                            Some(v) => FromValueRef::from_value_ref(v)?,
+                            None => 0.0,
+                        };
+                        Ok(Value::Bool(match animators.borrow_mut().get_mut(&sender_id(scope)) {
+                            Some(a) => a.animator.play(state, blend, &a.descr.animations),
+                            None => false,
+                        }))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Range(1, 2),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (position id), (velocity id) and (size id) give (x y) lists,
+            // or () if there is no such object
+            let accessors : [(&'static str, fn(&ObjectInfo) -> (fphys, fphys)); 3] = [
+                ("position", |o| (o.pos.0, o.pos.1)),
+                ("velocity", |o| (o.vel.0, o.vel.1)),
+                ("size", |o| (o.w.0, o.h.0)),
+            ];
+            for &(fun_name, accessor) in &accessors {
+                let world_view = self.world_view.clone();
+                scope.add_value_with_name(fun_name, move |lisp_name| {
+                    Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                        if args.len() == 1 {
+                            let id : u32 = FromValueRef::from_value_ref(&args[0])?;
+                            Ok(match world_view.borrow().get(id) {
+                                Some(info) => {
+                                    let (x, y) = accessor(info);
+                                    Value::from(vec![Value::Float(x), Value::Float(y)])
+                                }
+                                None => Value::Unit,
+                            })
+                        }
+                        else {
+                            Err(From::from(ExecError::ArityError{
+                                name: Some(lisp_name),
+                                expected: Arity::Exact(1 as u32),
+                                found: args.len() as u32,
+                            }))
+                        }
+                    })
+                });
+            }
+        }
+        {
+            // (hp id) gives (hp hp-max), or () for things that can't be hurt
+            let world_view = self.world_view.clone();
+            scope.add_value_with_name("hp", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 1 {
+                        let id : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        Ok(match world_view.borrow().get(id).and_then(|o| o.health) {
+                            Some((hp, hp_max)) => Value::from(vec![Value::Float(hp), Value::Float(hp_max)]),
+                            None => Value::Unit,
+                        })
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (object-name id) gives the name used by get-ids, eg. "player"
+            let world_view = self.world_view.clone();
+            scope.add_value_with_name("object-name", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 1 {
+                        let id : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        Ok(match world_view.borrow().get(id) {
+                            Some(info) => Value::from(info.name.as_str()),
+                            None => Value::Unit,
+                        })
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (objects-in-rect x y w h) gives the ids of overlapping objects
+            let world_view = self.world_view.clone();
+            scope.add_value_with_name("objects-in-rect", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 4 {
+                        let x : fphys = FromValueRef::from_value_ref(&args[0])?;
+                        let y : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let w : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        let h : fphys = FromValueRef::from_value_ref(&args[3])?;
+                        let rect = BoundingBox::new(Pos(x, y), Width(w), Height(h));
+                        Ok(Value::from(world_view.borrow().objects_in_rect(&rect)))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(4 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (nearest-of-type name x y) gives the closest other object
+            // called name, or ()
+            let world_view = self.world_view.clone();
+            scope.add_value_with_name("nearest-of-type", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 3 {
+                        let name : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let x : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let y : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        Ok(match world_view.borrow().nearest_of_type(name, Pos(x, y), sender_id(scope)) {
+                            Some(id) => Value::Integer(Integer::from_u32(id)),
+                            None => Value::Unit,
+                        })
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(3 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (raycast x1 y1 x2 y2 [flags]) gives (id x y) for the first
+            // object hit with any of the body flags, "block" by default
+            let world_view = self.world_view.clone();
+            let errors = self.errors.clone();
+            let script = name.to_owned();
+            scope.add_value_with_name("raycast", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 4 || args.len() == 5 {
+                        let x1 : fphys = FromValueRef::from_value_ref(&args[0])?;
+                        let y1 : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let x2 : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        let y2 : fphys = FromValueRef::from_value_ref(&args[3])?;
+                        let mask = match args.get(4) {
+                            Some(v) => {
+                                let flags : &str = FromValueRef::from_value_ref(v)?;
+                                parse_owner_type(flags).unwrap_or_else(|e| {
+                                    errors.borrow_mut().report(&script, e);
+                                    BBOwnerType::NONE
+                                })
+                            }
+                            None => BBOwnerType::BLOCK,
+                        };
+                        let hit = world_view.borrow()
+                            .raycast(Pos(x1, y1), Pos(x2, y2), mask, sender_id(scope));
+                        Ok(match hit {
+                            Some((id, Pos(x, y))) => Value::from(vec![
+                                Value::Integer(Integer::from_u32(id)),
+                                Value::Float(x),
+                                Value::Float(y)]),
+                            None => Value::Unit,
+                        })
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Range(4, 5),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // Each object owns at most one camera, setting a new one replaces it
+            // (camera-static x y w h priority [weight]) shows the whole rectangle
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            let cameras = self.cameras.clone();
+            scope.add_value_with_name("camera-static", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 5 || args.len() == 6 {
+                        let x : fphys = FromValueRef::from_value_ref(&args[0])?;
+                        let y : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let w : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        let h : fphys = FromValueRef::from_value_ref(&args[3])?;
+                        let priority : u32 = FromValueRef::from_value_ref(&args[4])?;
+                        let weight : fphys = match args.get(5) {
+                            Some(v) => FromValueRef::from_value_ref(v)?,
+                            None => SCRIPT_CAMERA_WEIGHT,
+                        };
+                        let camera = ViewArena::new(Rectangle::new(x, y, w, h),
+                                                    weight,
+                                                    CameraPriority(priority));
+                        let id = sender_id(scope);
+                        cameras.borrow_mut().insert(id);
+                        metabuffer_tx.send(MetaCommand::AddCamera(id, Box::new(camera))).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Range(5, 6),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (camera-follow id priority [weight scale-weight zoom]), any left
+            // out take the defaults
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            let cameras = self.cameras.clone();
+            scope.add_value_with_name("camera-follow", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() >= 2 && args.len() <= 5 {
+                        let target : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        let priority : u32 = FromValueRef::from_value_ref(&args[1])?;
+                        let arg_or = |i : usize, default : fphys| -> Result<fphys, ExecError> {
+                            match args.get(i) {
+                                Some(v) => FromValueRef::from_value_ref(v),
+                                None => Ok(default),
+                            }
+                        };
+                        let camera = ViewFollower::new(target,
+                                                       CameraPriority(priority),
+                                                       arg_or(2, SCRIPT_CAMERA_WEIGHT)?,
+                                                       arg_or(3, SCRIPT_CAMERA_WEIGHT)?,
+                                                       arg_or(4, 1.0)?);
+                        let id = sender_id(scope);
+                        cameras.borrow_mut().insert(id);
+                        metabuffer_tx.send(MetaCommand::AddCamera(id, Box::new(camera))).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Range(2, 5),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (camera-pop) hands the view back to whatever was below
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            let cameras = self.cameras.clone();
+            scope.add_value_with_name("camera-pop", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |scope, args| {
+                    if args.len() == 0 {
+                        let id = sender_id(scope);
+                        cameras.borrow_mut().remove(&id);
+                        metabuffer_tx.send(MetaCommand::RemoveCamera(id)).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(0 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // (camera-shake intensity secs) and (camera-punch amount secs)
+            let effects : [(&'static str, fn(fphys, fphys) -> MetaCommand); 2] = [
+                ("camera-shake", MetaCommand::CameraShake),
+                ("camera-punch", MetaCommand::CameraPunch),
+            ];
+            for &(fun_name, command) in &effects {
+                let metabuffer_tx = self.metabuffer_tx.clone();
+                scope.add_value_with_name(fun_name, move |lisp_name| {
+                    Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                        if args.len() == 2 {
+                            let strength : fphys = FromValueRef::from_value_ref(&args[0])?;
+                            let secs : fphys = FromValueRef::from_value_ref(&args[1])?;
+                            metabuffer_tx.send(command(strength, secs)).unwrap();
+                            Ok(Value::Unit)
+                        }
+                        else {
+                            Err(From::from(ExecError::ArityError{
+                                name: Some(lisp_name),
+                                expected: Arity::Exact(2 as u32),
+                                found: args.len() as u32,
+                            }))
+                        }
+                    })
+                });
+            }
+        }
+        {
+            let metabuffer_tx = self.metabuffer_tx.clone();
+            scope.add_value_with_name("apply-force", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 3 {
+                        let id : u32 = FromValueRef::from_value_ref(&args[0])?;
+                        let fx : fphys = FromValueRef::from_value_ref(&args[1])?;
+                        let fy : fphys = FromValueRef::from_value_ref(&args[2])?;
+                        metabuffer_tx.send(MetaCommand::ApplyForce(id, Force(fx, fy))).unwrap();
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(3 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            let inventory = self.inventory.clone();
+            scope.add_value_with_name("inventory-count", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 1 {
+                        let item : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let count = inventory.lock().unwrap().count(item);
+                        Ok(Value::Integer(Integer::from_u32(count)))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(1 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            let inventory = self.inventory.clone();
+            scope.add_value_with_name("inventory-add", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 2 {
+                        let item : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let amount : u32 = FromValueRef::from_value_ref(&args[1])?;
+                        inventory.lock().unwrap().add(item, amount);
+                        Ok(Value::Unit)
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(2 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
+        {
+            // Returns false if there weren't enough to remove
+            let inventory = self.inventory.clone();
+            scope.add_value_with_name("inventory-remove", move |lisp_name| {
+                Value::new_foreign_fn(lisp_name, move |_scope, args| {
+                    if args.len() == 2 {
+                        let item : &str = FromValueRef::from_value_ref(&args[0])?;
+                        let amount : u32 = FromValueRef::from_value_ref(&args[1])?;
+                        let removed = inventory.lock().unwrap().remove(item, amount);
+                        Ok(Value::Bool(removed))
+                    }
+                    else {
+                        Err(From::from(ExecError::ArityError{
+                            name: Some(lisp_name),
+                            expected: Arity::Exact(2 as u32),
+                            found: args.len() as u32,
+                        }))
+                    }
+                })
+            });
+        }
 
     }
 
-    fn default_scope(&self, id : Id, name : &str) -> GlobalScope {
-        let mut ds = GlobalScope::default(name);
-        ds.register_struct_value::<super::player::PlayerDynState>();
-        ketos_fn!{ ds => "chr" => fn chr(x : u32) -> char }
-        self.add_logic_funs(id, &ds);
-        ds
+    fn add_default_funs(&self, id : Id, name : &str, scope : &GlobalScope) {
+        scope.register_struct_value::<Body>();
+        ketos_fn!{ scope => "chr" => fn chr(x : u32) -> char }
+        self.add_logic_funs(id, name, scope);
     }
 
-    fn new_interpreter(&self, id : Id, name : &str) -> Option<Interpreter> {
-        let scope = Rc::new(self.default_scope(id, name));
-        let interp = Builder::new()
-            .scope(scope)
-            .finish();
+    // Interpreter with the engine functions and default events but no script
+    fn new_bare_interpreter(&self, id : Id, name : &str) -> Interpreter {
+        let deps = Rc::new(RefCell::new(HashSet::new()));
+        self.script_deps.borrow_mut().insert(name.to_owned(), deps.clone());
+        self.new_bare_interpreter_from(id, name, None, &deps)
+    }
+
+    // As new_bare_interpreter, sharing the names and code map of previous
+    // so values it made, like states being migrated, mean the same here
+    // Library modules loaded are added to deps
+    fn new_bare_interpreter_from(&self,
+                                 id : Id,
+                                 name : &str,
+                                 previous : Option<&Interpreter>,
+                                 deps : &Rc<RefCell<HashSet<String>>>) -> Interpreter {
+        let mut builder = Builder::new()
+            .restrict(sandbox_config())
+            .module_loader(Box::new(SandboxModuleLoader::new(deps.clone())));
+        if let Some(previous) = previous {
+            builder = builder
+                .name_store(previous.scope().names().clone())
+                .codemap(previous.scope().codemap().clone());
+        }
+        let interp = builder.finish();
+        self.add_default_funs(id, name, interp.scope());
         interp.run_code(r#"
-            (define (init-state)  ())
+            (define (state-init args) ())
+            (define (field fields name default)
+              (let ((found (filter (lambda (f) (= (first f) name)) fields)))
+                (if (null found) default (second (first found)))))
             (define (tick state) state)
             (define (press state key) (do (println "KeyPress ~a" key) state))
             (define (release state key) state)
             (define (draw state) ())
+            (define (on-destroy state) ())
+            (define (migrate-state state) state)
+            (define (body state) ())
+            (define (on-collision state other kind) state)
+            (define (on-message state from msg) state)
+            (define (on-trigger state) state)
+            (define (anim-event state anim-state event) state)
+            (define (wait secs) secs)
             "#, None).unwrap();
+        interp
+    }
+
+    fn new_interpreter(&self, id : Id, name : &str) -> Option<Interpreter> {
+        self.new_interpreter_from(id, name, None)
+    }
+
+    fn new_interpreter_from(&self, id : Id, name : &str, previous : Option<&Interpreter>) -> Option<Interpreter> {
+        let deps = Rc::new(RefCell::new(HashSet::new()));
+        let interp = self.new_bare_interpreter_from(id, name, previous, &deps);
         match interp.run_file(Path::new(name)) {
-            Ok(()) => Some(interp),
-            Err(e) => { 
-                println!("Compile error for {}", name); 
-                display_error(&interp, &e);
+            Ok(()) => {
+                self.script_deps.borrow_mut().insert(name.to_owned(), deps);
+                Some(interp)
+            }
+            Err(e) => {
+                // A script that fails to compile keeps its old interpreter
+                // and that one's deps, one that never compiled is reloaded
+                // when any module it got as far as using changes
+                if !self.interpreters.contains_key(name) {
+                    self.script_deps.borrow_mut().insert(name.to_owned(), deps);
+                }
+                self.script_error(name, &interp, &e);
                 None
             }
         }
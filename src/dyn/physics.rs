@@ -0,0 +1,136 @@
+use collision::*;
+use draw::Drawable;
+use game::{CommandBuffer, Force, Height, Id, Mass, MetaCommand, Pos, Vel, Width, fphys};
+use physics::{Physical, PhysDyn, PhysStatic};
+use piston::input::*;
+use std::sync::{Arc, Mutex};
+use world::World;
+
+use ketos::Value;
+
+// Body declared by a script's (body state) function, eg.
+//  (new Body :kind "dynamic" :width 20.0 :height 20.0 :mass 1.0
+//            :maxspeed 300.0 :flags "enemy" :collide "block platform")
+#[derive(Clone, Debug, ForeignValue, FromValueClone, StructValue)]
+pub struct Body {
+    // "static", "dynamic" or "sensor"
+    kind : String,
+    width : fphys,
+    height : fphys,
+    mass : fphys,
+    maxspeed : fphys,
+    // Space separated BBOwnerType names for this body
+    flags : String,
+    // Space separated BBOwnerType names to resolve collisions against
+    collide : String,
+}
+
+const FLAG_NAMES : [(&'static str, BBOwnerType); 8] = [
+    ("platform", BBOwnerType::PLATFORM),
+    ("block", BBOwnerType::BLOCK),
+    ("player", BBOwnerType::PLAYER),
+    ("enemy", BBOwnerType::ENEMY),
+    ("damage", BBOwnerType::DAMAGE),
+    ("player_entity", BBOwnerType::PLAYER_ENTITY),
+    ("nocollide", BBOwnerType::NOCOLLIDE),
+    ("nograpple", BBOwnerType::NOGRAPPLE),
+];
+
+pub fn parse_owner_type(names : &str) -> Result<BBOwnerType, String> {
+    let mut owner_type = BBOwnerType::NONE;
+    for name in names.split_whitespace() {
+        match FLAG_NAMES.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, flag)) => owner_type |= flag,
+            None => return Err(format!("Unknown body flag {}", name)),
+        }
+    }
+    Ok(owner_type)
+}
+
+pub fn owner_type_to_lisp(owner_type : BBOwnerType) -> Value {
+    let names = FLAG_NAMES.iter()
+        .filter(|&&(_, flag)| owner_type.contains(flag))
+        .map(|&(n, _)| Value::from(n))
+        .collect::<Vec<Value>>();
+    Value::from(names)
+}
+
+// Physics for scripted objects
+// Behaves like PhysNone until the script declares a body
+pub struct DynPhysics {
+    id : Id,
+    body : Option<Box<Physical>>,
+    draw : Arc<Mutex<Drawable>>,
+}
+
+impl DynPhysics {
+    pub fn new(id : Id, draw : Arc<Mutex<Drawable>>) -> Self {
+        DynPhysics {
+            id : id,
+            body : None,
+            draw : draw,
+        }
+    }
+
+    pub fn has_body(&self) -> bool {
+        self.body.is_some()
+    }
+
+    pub fn set_body(&mut self, body : &Body, pos : Pos, world : &World) -> Result<(), String> {
+        let props = BBProperties::new(self.id, parse_owner_type(&body.flags)?);
+        let w = Width(body.width);
+        let h = Height(body.height);
+        let physics : Box<Physical> = match body.kind.as_str() {
+            "static" => Box::new(PhysStatic::new(props, pos, w, h, world)),
+            "dynamic" | "sensor" => {
+                let sensor = body.kind == "sensor";
+                let mut phys = PhysDyn::new(props,
+                                            pos,
+                                            Mass(body.mass),
+                                            body.maxspeed,
+                                            w,
+                                            h,
+                                            !sensor,
+                                            self.draw.clone());
+                phys.collide_with = parse_owner_type(&body.collide)?;
+                Box::new(phys)
+            }
+            _ => return Err(format!("Unknown body kind {}", body.kind)),
+        };
+        self.body = Some(physics);
+        Ok(())
+    }
+}
+
+impl Physical for DynPhysics {
+    fn tick(&mut self,
+            args : &UpdateArgs,
+            metabuffer : &CommandBuffer<MetaCommand>,
+            world : &World) {
+        self.body.as_mut().map(|b| b.tick(args, metabuffer, world));
+    }
+    fn apply_force(&mut self, f : Force) {
+        self.body.as_mut().map(|b| b.apply_force(f));
+    }
+    fn get_position(&self) -> Pos {
+        self.body.as_ref().map(|b| b.get_position()).unwrap_or(Pos(0.0, 0.0))
+    }
+    fn get_width_height(&self) -> (Width, Height) {
+        self.body.as_ref().map(|b| b.get_width_height()).unwrap_or((Width(0.0), Height(0.0)))
+    }
+    fn get_vel(&self) -> Vel {
+        self.body.as_ref().map(|b| b.get_vel()).unwrap_or(Vel(0.0, 0.0))
+    }
+    fn get_id(&self) -> Id {
+        self.id
+    }
+    fn set_velocity(&mut self, v : Vel) {
+        self.body.as_mut().map(|b| b.set_velocity(v));
+    }
+    fn set_position(&mut self, p : Pos) {
+        self.body.as_mut().map(|b| b.set_position(p));
+    }
+    fn destroy(&mut self, world : &World) {
+        self.body.as_mut().map(|b| b.destroy(world));
+    }
+}
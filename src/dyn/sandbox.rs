@@ -0,0 +1,79 @@
+// Limits placed on scripts so a broken one can't take down the engine
+//
+// Scripts that go over their limits are disabled until they are next
+// reloaded, see DynMap::script_error
+//
+// Besides the allowed builtin modules scripts can (use ...) library
+// modules from scripts/lib, eg. (use vec (vec-add)) loads scripts/lib/vec.ket
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use ketos::{BuiltinModuleLoader, Context, Error, FileModuleLoader, Module, ModuleLoader, Name, RestrictConfig};
+
+// Time allowed for a single call into a script, eg. one tick or draw
+const SCRIPT_TIME_LIMIT_MS: u64 = 50;
+// Roughly the number of values a script may hold at once
+const SCRIPT_MEMORY_LIMIT: usize = 1 << 20;
+
+// Builtin modules scripts may (use ...), other names are looked up in
+// the library path
+const MODULE_ALLOWLIST: [&'static str; 2] = ["math", "random"];
+
+pub const LIB_PATH: &'static str = "scripts/lib";
+const LIB_EXTENSION: &'static str = "ket";
+
+// Name of the library module at path, if it is one
+pub fn lib_module_name(path: &Path) -> Option<String> {
+    let in_lib = path.parent().map_or(false, |p| p.ends_with(LIB_PATH));
+    let is_module = path.extension().map_or(false, |e| e == LIB_EXTENSION);
+    if in_lib && is_module {
+        path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_owned())
+    } else {
+        None
+    }
+}
+
+pub fn sandbox_config() -> RestrictConfig {
+    let mut config = RestrictConfig::permissive();
+    config.execution_time = Some(Duration::from_millis(SCRIPT_TIME_LIMIT_MS));
+    config.memory_limit = SCRIPT_MEMORY_LIMIT;
+    config
+}
+
+pub struct SandboxModuleLoader {
+    builtin: BuiltinModuleLoader,
+    lib: FileModuleLoader,
+    // Every library module the interpreter asked for, including ones
+    // used by other modules, so changes can reload the script
+    deps: Rc<RefCell<HashSet<String>>>,
+}
+
+impl SandboxModuleLoader {
+    pub fn new(deps: Rc<RefCell<HashSet<String>>>) -> Self {
+        let mut lib = FileModuleLoader::with_search_paths(vec![PathBuf::from(LIB_PATH)]);
+        // Compiled files would go stale on hot reload
+        lib.set_read_bytecode(false);
+        lib.set_write_bytecode(false);
+        SandboxModuleLoader {
+            builtin: BuiltinModuleLoader,
+            lib: lib,
+            deps: deps,
+        }
+    }
+}
+
+impl ModuleLoader for SandboxModuleLoader {
+    fn load_module(&self, name: Name, ctx: Context) -> Result<Module, Error> {
+        let module_name = ctx.scope().with_name(name, |s| s.to_owned());
+        if MODULE_ALLOWLIST.contains(&module_name.as_str()) {
+            return self.builtin.load_module(name, ctx);
+        }
+        // Recorded even if loading fails so fixing the module reloads it
+        self.deps.borrow_mut().insert(module_name);
+        self.lib.load_module(name, ctx)
+    }
+}
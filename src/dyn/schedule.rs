@@ -0,0 +1,245 @@
+// Engine side timers, tweens and sequences for scripted objects
+//
+// Callbacks are named events in the object's script rather than lambdas
+// so they keep working when the script is hot reloaded:
+//  (after 2.0 "explode")             calls (explode state) in 2 seconds
+//  (every 0.5 "spawn" 3)             calls (spawn state 3) every half second
+//  (tween "fade" 0.0 1.0 1.5 "ease-out-quad")
+//                                    calls (fade state value) each tick
+//  (sequence (list "walk-in" (wait 1.0) "talk" (wait 2.0) "leave"))
+//                                    calls each event in turn, pausing at waits
+// Each returns a handle which can be passed to (cancel handle)
+
+use std::collections::VecDeque;
+
+use game::fphys;
+
+use ketos::{Integer, Value};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+}
+
+// Every curve by name, which is how scripts refer to them both in
+// (tween ...) and as functions of their own, eg. (ease-out-quad 0.5)
+pub const EASES : [(&'static str, Ease); 9] = [
+    ("ease-linear", Ease::Linear),
+    ("ease-in-quad", Ease::InQuad),
+    ("ease-out-quad", Ease::OutQuad),
+    ("ease-in-out-quad", Ease::InOutQuad),
+    ("ease-in-cubic", Ease::InCubic),
+    ("ease-out-cubic", Ease::OutCubic),
+    ("ease-in-out-cubic", Ease::InOutCubic),
+    ("ease-in-sine", Ease::InSine),
+    ("ease-out-sine", Ease::OutSine),
+];
+
+impl Ease {
+    pub fn parse(name : &str) -> Option<Ease> {
+        EASES.iter()
+            .find(|&&(ease_name, _)| ease_name == name)
+            .map(|&(_, ease)| ease)
+    }
+
+    pub fn apply(&self, t : fphys) -> fphys {
+        use std::f64::consts::PI;
+        let t = t.max(0.0).min(1.0);
+        match *self {
+            Ease::Linear => t,
+            Ease::InQuad => t * t,
+            Ease::OutQuad => t * (2.0 - t),
+            Ease::InOutQuad => {
+                if t < 0.5 { 2.0 * t * t } else { (4.0 - 2.0 * t) * t - 1.0 }
+            }
+            Ease::InCubic => t * t * t,
+            Ease::OutCubic => {
+                let u = t - 1.0;
+                u * u * u + 1.0
+            }
+            Ease::InOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let u = 2.0 * t - 2.0;
+                    0.5 * u * u * u + 1.0
+                }
+            }
+            Ease::InSine => 1.0 - (t * PI / 2.0).cos(),
+            Ease::OutSine => (t * PI / 2.0).sin(),
+        }
+    }
+}
+
+pub type Handle = u32;
+
+// An event to run in the object's script with the given arguments
+pub type Call = (String, Vec<Value>);
+
+struct Timer {
+    handle : Handle,
+    left : fphys,
+    // Period for timers created with every
+    every : Option<fphys>,
+    event : String,
+    arg : Option<Value>,
+}
+
+struct Tween {
+    handle : Handle,
+    elapsed : fphys,
+    duration : fphys,
+    from : fphys,
+    to : fphys,
+    ease : Ease,
+    event : String,
+}
+
+pub enum SequenceStep {
+    Call(String),
+    Wait(fphys),
+}
+
+struct Sequence {
+    handle : Handle,
+    steps : VecDeque<SequenceStep>,
+    wait : fphys,
+}
+
+pub struct Schedule {
+    next_handle : Handle,
+    timers : Vec<Timer>,
+    tweens : Vec<Tween>,
+    sequences : Vec<Sequence>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule {
+            next_handle : 1,
+            timers : Vec::new(),
+            tweens : Vec::new(),
+            sequences : Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty() && self.tweens.is_empty() && self.sequences.is_empty()
+    }
+
+    fn new_handle(&mut self) -> Handle {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        handle
+    }
+
+    pub fn after(&mut self, delay : fphys, event : String, arg : Option<Value>) -> Handle {
+        let handle = self.new_handle();
+        self.timers.push(Timer {
+            handle : handle,
+            left : delay,
+            every : None,
+            event : event,
+            arg : arg,
+        });
+        handle
+    }
+
+    pub fn every(&mut self, period : fphys, event : String, arg : Option<Value>) -> Handle {
+        let handle = self.new_handle();
+        self.timers.push(Timer {
+            handle : handle,
+            left : period,
+            every : Some(period),
+            event : event,
+            arg : arg,
+        });
+        handle
+    }
+
+    pub fn tween(&mut self,
+                 event : String,
+                 from : fphys,
+                 to : fphys,
+                 duration : fphys,
+                 ease : Ease) -> Handle {
+        let handle = self.new_handle();
+        self.tweens.push(Tween {
+            handle : handle,
+            elapsed : 0.0,
+            duration : duration,
+            from : from,
+            to : to,
+            ease : ease,
+            event : event,
+        });
+        handle
+    }
+
+    pub fn sequence(&mut self, steps : Vec<SequenceStep>) -> Handle {
+        let handle = self.new_handle();
+        self.sequences.push(Sequence {
+            handle : handle,
+            steps : steps.into_iter().collect(),
+            wait : 0.0,
+        });
+        handle
+    }
+
+    pub fn cancel(&mut self, handle : Handle) {
+        self.timers.retain(|t| t.handle != handle);
+        self.tweens.retain(|t| t.handle != handle);
+        self.sequences.retain(|s| s.handle != handle);
+    }
+
+    // Move time on by dt, returning the events that are due in order
+    pub fn advance(&mut self, dt : fphys) -> Vec<Call> {
+        let mut calls = Vec::new();
+
+        for timer in &mut self.timers {
+            timer.left -= dt;
+            if timer.left <= 0.0 {
+                calls.push((timer.event.clone(), timer.arg.iter().cloned().collect()));
+                if let Some(period) = timer.every {
+                    // Keep the remainder so long periods don't drift
+                    timer.left += period.max(dt);
+                }
+            }
+        }
+        self.timers.retain(|t| t.left > 0.0);
+
+        for tween in &mut self.tweens {
+            tween.elapsed = (tween.elapsed + dt).min(tween.duration);
+            let t = if tween.duration > 0.0 { tween.elapsed / tween.duration } else { 1.0 };
+            let value = tween.from + (tween.to - tween.from) * tween.ease.apply(t);
+            calls.push((tween.event.clone(), vec![Value::Float(value)]));
+        }
+        self.tweens.retain(|t| t.elapsed < t.duration);
+
+        for sequence in &mut self.sequences {
+            sequence.wait -= dt;
+            while sequence.wait <= 0.0 {
+                match sequence.steps.pop_front() {
+                    Some(SequenceStep::Call(event)) => calls.push((event, Vec::new())),
+                    Some(SequenceStep::Wait(secs)) => sequence.wait += secs,
+                    None => break,
+                }
+            }
+        }
+        self.sequences.retain(|s| !s.steps.is_empty() || s.wait > 0.0);
+
+        calls
+    }
+}
+
+pub fn handle_to_lisp(handle : Handle) -> Value {
+    Value::Integer(Integer::from_u32(handle))
+}
@@ -0,0 +1,137 @@
+// Read only snapshot of every object in the game for scripts to query
+//
+// Rebuilt by the game loop each tick before any logic runs, so scripts
+// see where things were at the start of the tick.
+
+use collision::{BBOwnerType, BoundingBox};
+use game::{GameObj, Height, Id, Pos, Vel, Width, fphys};
+use std::collections::HashMap;
+use world::World;
+
+#[derive(Clone)]
+pub struct ObjectInfo {
+    pub name : String,
+    pub pos : Pos,
+    pub vel : Vel,
+    pub w : Width,
+    pub h : Height,
+    // NONE for objects without a bounding box in the world
+    pub owner_type : BBOwnerType,
+    // Current and max hp for anything that can be damaged
+    pub health : Option<(fphys, fphys)>,
+}
+
+impl ObjectInfo {
+    fn bb(&self) -> BoundingBox {
+        BoundingBox::new(self.pos, self.w, self.h)
+    }
+
+    fn centre(&self) -> Pos {
+        let Pos(x, y) = self.pos;
+        Pos(x + self.w.0 / 2.0, y + self.h.0 / 2.0)
+    }
+}
+
+pub struct WorldView {
+    objects : HashMap<Id, ObjectInfo>,
+}
+
+impl WorldView {
+    pub fn new() -> Self {
+        WorldView { objects : HashMap::new() }
+    }
+
+    pub fn update(&mut self, objs : &[GameObj], world : &World) {
+        self.objects.clear();
+        for o in objs {
+            let (pos, vel, (w, h)) = {
+                let p = o.physics.lock().unwrap();
+                (p.get_position(), p.get_vel(), p.get_width_height())
+            };
+            let owner_type = world.get(o.id)
+                .map(|(props, _)| props.owner_type)
+                .unwrap_or(BBOwnerType::NONE);
+            self.objects.insert(o.id, ObjectInfo {
+                name : o.name.clone(),
+                pos : pos,
+                vel : vel,
+                w : w,
+                h : h,
+                owner_type : owner_type,
+                health : world.health(o.id),
+            });
+        }
+    }
+
+    // For the test harness, which has no game objects to build it from
+    pub fn insert(&mut self, id : Id, info : ObjectInfo) {
+        self.objects.insert(id, info);
+    }
+
+    pub fn get(&self, id : Id) -> Option<&ObjectInfo> {
+        self.objects.get(&id)
+    }
+
+    // Ids of objects overlapping the rectangle, in id order
+    pub fn objects_in_rect(&self, rect : &BoundingBox) -> Vec<Id> {
+        let mut ids = self.objects.iter()
+            .filter(|&(_, info)| info.bb().check_col(rect))
+            .map(|(id, _)| *id)
+            .collect::<Vec<Id>>();
+        ids.sort();
+        ids
+    }
+
+    // Closest object called name to pos, measured between centres
+    pub fn nearest_of_type(&self, name : &str, pos : Pos, exclude : Id) -> Option<Id> {
+        let Pos(x, y) = pos;
+        self.objects.iter()
+            .filter(|&(id, info)| *id != exclude && info.name == name)
+            .map(|(id, info)| {
+                let Pos(cx, cy) = info.centre();
+                (*id, (cx - x) * (cx - x) + (cy - y) * (cy - y))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(id, _)| id)
+    }
+
+    // First object matching mask hit by the segment from start to end,
+    // with the point it was hit at
+    pub fn raycast(&self, start : Pos, end : Pos, mask : BBOwnerType, exclude : Id) -> Option<(Id, Pos)> {
+        let Pos(x0, y0) = start;
+        let Pos(x1, y1) = end;
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        self.objects.iter()
+            .filter(|&(id, info)| *id != exclude && info.owner_type.intersects(mask))
+            .filter_map(|(id, info)| {
+                let Pos(bx, by) = info.pos;
+                segment_hits_box(x0, y0, dx, dy, bx, by, bx + info.w.0, by + info.h.0)
+                    .map(|t| (*id, t))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(id, t)| (id, Pos(x0 + dx * t, y0 + dy * t)))
+    }
+}
+
+// Slab test, returns how far along the segment the box is entered
+fn segment_hits_box(x0 : fphys, y0 : fphys, dx : fphys, dy : fphys,
+                    left : fphys, top : fphys, right : fphys, bottom : fphys) -> Option<fphys> {
+    let mut t_min : fphys = 0.0;
+    let mut t_max : fphys = 1.0;
+    for &(p, d, lo, hi) in &[(x0, dx, left, right), (y0, dy, top, bottom)] {
+        if d == 0.0 {
+            if p < lo || p > hi {
+                return None;
+            }
+        } else {
+            let t1 = (lo - p) / d;
+            let t2 = (hi - p) / d;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+            if t_min > t_max {
+                return None;
+            }
+        }
+    }
+    Some(t_min)
+}
@@ -2,6 +2,7 @@ extern crate rand;
 
 use self::EnemyState::*;
 use self::rand::{Rng, thread_rng};
+use animator::Animator;
 use collision::*;
 use descriptors::{Descriptor, HumanoidDescriptor, EnemyDescriptor, WorldDescriptor};
 use draw::GrphxRect;
@@ -29,6 +30,8 @@ struct EnemyLogic {
     id: Id,
     weapon: Box<Wieldable>,
     weapon_cd: fphys,
+    //  Target of an attack waiting for the animation's "attack" frame
+    attack_target: Option<Pos>,
     physics: Arc<Mutex<PhysDyn>>,
     draw: Arc<Mutex<EnemyGphx>>,
     state: EnemyState,
@@ -38,7 +41,8 @@ struct EnemyLogic {
     faction: Faction,
     cds: Cooldowns,
     hp: fphys,
-    spawn_pos: Pos,
+    //  Where to reappear after dying, None to be removed instead
+    spawn_pos: Option<Pos>,
 }
 
 //  TODO code reuse from player
@@ -48,17 +52,50 @@ impl Logical for EnemyLogic {
 
         let phys_info = get_phys_info(self.physics.clone());
         let Pos(x, y) = phys_info.pos;
-        let Vel(xvel, _yvel) = phys_info.vel;
+        let Vel(xvel, yvel) = phys_info.vel;
         let dt = args.piston.dt as fphys;
 
         if self.hp <= 0.0 || y > MAX_HEIGHT {
-            //args.metabuffer.issue(MetaCommand::RemoveObject(self.id));
-            let mut ppp = self.physics.lock().unwrap();
-            ppp.set_position(self.spawn_pos);
+            match self.spawn_pos {
+                Some(spawn_pos) => {
+                    //  Back at full health, or it would respawn every tick
+                    self.hp = self.descr.start_hp;
+                    let mut ppp = self.physics.lock().unwrap();
+                    ppp.set_position(spawn_pos);
+                    ppp.set_velocity(Vel(0.0, 0.0));
+                }
+                None => {
+                    args.world.remove_fighter(self.id);
+                    args.metabuffer.issue(MetaCommand::RemoveObject(self.id));
+                }
+            }
             return;
         }
 
-        self.collision_buffer = buffer_collisions(args.message_buffer);
+        self.collision_buffer = Vec::new();
+        for m in args.message_buffer.read_buffer() {
+            match m {
+                ObjMessage::MCollision(c) => {
+                    self.collision_buffer.push(c);
+                }
+                ObjMessage::MDamage(dmg) => {
+                    self.hp -= dmg;
+                }
+                ObjMessage::MDescriptorsReloaded(reloaded) => {
+                    if let Some(descr) = self.descr.reloaded(&reloaded) {
+                        self.descr = descr.clone();
+                        let mut d = self.draw.lock().unwrap();
+                        d.animator.set_descriptor(descr.animator.clone());
+                        d.manager = descr;
+                    }
+                    if let Some(ref world_descr) = reloaded.world {
+                        self.world_descr = world_descr.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+        args.world.report_health(self.id, self.hp, self.descr.start_hp);
 
         //  Handle collisions
         for c in &self.collision_buffer {
@@ -127,10 +164,7 @@ impl Logical for EnemyLogic {
                 //  Weapon handling
                 if self.weapon_cd <= 0.0 {
                     self.weapon_cd = self.weapon.get_cd();
-                    self.weapon.fire(Pos(tx, ty),
-                                     Pos(x + phys_info.w.0 / 2.0,
-                                         y + phys_info.h.0 / 2.0),
-                                     args);
+                    self.attack_target = Some(Pos(tx, ty));
                 } else {
                     self.weapon_cd -= dt;
                 }
@@ -141,7 +175,8 @@ impl Logical for EnemyLogic {
 
 
 
-        {
+        let on_ground = self.physics.lock().unwrap().on_ground;
+        let events = {
             let mut d = self.draw.lock().unwrap();
             if xvel > 1.0 {
                 d.reverse = false;
@@ -149,6 +184,21 @@ impl Logical for EnemyLogic {
             if xvel < -1.0 {
                 d.reverse = true;
             }
+            d.animator.set_bool("on_ground", on_ground);
+            d.animator.set("speed", xvel.abs());
+            d.animator.set("yvel", yvel);
+            d.animator.set_bool("attacking", self.attack_target.is_some());
+            d.animator.update(dt, &self.descr.animations)
+        };
+
+        //  Attacks land on the "attack" frame if the animator has one
+        if let Some(target) = self.attack_target {
+            if !self.descr.animator.fires("attack") || events.iter().any(|e| e == "attack") {
+                self.attack_target = None;
+                self.weapon.fire(target,
+                                 Pos(x + phys_info.w.0 / 2.0, y + phys_info.h.0 / 2.0),
+                                 args);
+            }
         }
 
         humanoid_input(args,
@@ -196,16 +246,34 @@ pub fn create(id: Id,
               world: &World,
               faction: Faction)
               -> GameObj {
+    create_with_respawn(id, pos, descr, world, faction, Some(pos))
+}
+
+//  Spawned enemies are removed when they die rather than respawning
+pub fn create_spawned(id: Id,
+                      pos: Pos,
+                      descr: Rc<EnemyDescriptor>,
+                      world: &World,
+                      faction: Faction)
+                      -> GameObj {
+    create_with_respawn(id, pos, descr, world, faction, None)
+}
+
+fn create_with_respawn(id: Id,
+                       pos: Pos,
+                       descr: Rc<EnemyDescriptor>,
+                       world: &World,
+                       faction: Faction,
+                       spawn_pos: Option<Pos>)
+                       -> GameObj {
 
     world.add_fighter(id, faction);
     let graphics = EnemyGphx {
         pos: pos,
         scale: descr.scale,
-        speed: descr.speed,
-        state: EnemyDrawState::Idle,
+        animator: Animator::new(descr.animator.clone()),
         reverse: false,
         manager: descr.clone(),
-        frame: 1.0,
     };
     let g = arc_mut(graphics);
     let props = BBProperties::new(id, BBOwnerType::ENEMY);
@@ -220,13 +288,14 @@ pub fn create(id: Id,
     phys.collide_with = BBOwnerType::BLOCK | BBOwnerType::PLATFORM;
     let p = arc_mut(phys);
 
-    let weapon = Box::new(Bow {});
+    let weapon = descr.weapon.to_wieldable();
     let name = descr.name.clone();
     let l = arc_mut(EnemyLogic {
         id: id,
-        spawn_pos: pos,
+        spawn_pos: spawn_pos,
         weapon: weapon,
         weapon_cd: 0.0,
+        attack_target: None,
         faction: faction,
         physics: p.clone(),
         state: EnemyIdle(None),
@@ -1,36 +1,28 @@
+use animator::Animator;
 use descriptors::EnemyDescriptor;
 use draw::{Color, Rectangle};
-use draw::{Drawable, ViewTransform};
+use draw::{Drawable, SpriteBatch, ViewTransform};
 use game::{Pos, fphys};
 use graphics::{image, polygon};
 use graphics::ImageSize;
-use graphics::Transformed;
+use graphics::{Context, Transformed};
+use graphics::math::Matrix2d;
 use graphics::math::Vec2d;
 use opengl_graphics::{Filter, GlGraphics};
 use opengl_graphics::Texture;
 use piston::input::*;
-use player_graphics::get_index;
 
 use std::ops::Rem;
 
 use std::rc::Rc;
 
-pub enum EnemyDrawState {
-    Idle,
-    Run,
-    Jump,
-    Attack,
-}
-
-
 pub struct EnemyGphx {
     pub pos: Pos,
     pub scale: fphys,
-    pub speed: fphys,
-    pub state: EnemyDrawState,
+    //  Stepped by the enemy's logic, which sets its parameters
+    pub animator: Animator,
     pub reverse: bool,
     pub manager: Rc<EnemyDescriptor>,
-    pub frame: f64,
 }
 
 fn arc(centre: (fphys, fphys),
@@ -56,26 +48,29 @@ fn arc(centre: (fphys, fphys),
 
 const PI: fphys = 3.141;
 
+impl EnemyGphx {
+    //  Where the current frame goes, flipped when facing left, None if
+    //  there is no animation to show
+    fn transform(&self, vt: &ViewTransform, c: &Context) -> Option<Matrix2d> {
+        let anim = self.animator.animation(&self.manager.animations)?;
+        let w = self.scale * anim.size().0;
+        Some(if self.reverse {
+            vt.transform(self.pos.0 + w, self.pos.1, -self.scale, self.scale, c)
+        } else {
+            vt.transform(self.pos.0, self.pos.1, self.scale, self.scale, c)
+        })
+    }
+}
+
 impl Drawable for EnemyGphx {
     fn draw(&mut self,
             args: &RenderArgs,
             ctx: &mut GlGraphics,
             vt: &ViewTransform) {
-        self.frame += 1.0;
-        let texture_vec = match self.state {
-            EnemyDrawState::Idle => &self.manager.idle,
-            EnemyDrawState::Run => &self.manager.running,
-            EnemyDrawState::Jump => &self.manager.jumping,
-            EnemyDrawState::Attack => &self.manager.attacking,
-        };
-        let texture = get_index(self.frame, texture_vec, self.speed);
         ctx.draw(args.viewport(), |c, gl| {
-
-            let transform = if self.reverse {
-                vt.transform(self.pos.0 + self.scale*(texture.get_width() as fphys), self.pos.1, -self.scale, self.scale, &c)
-            }
-            else {
-                vt.transform(self.pos.0, self.pos.1, self.scale, self.scale, &c)
+            let transform = match self.transform(vt, &c) {
+                Some(t) => t,
+                None => return,
             };
 
             let _cone_angle_base = if self.reverse {
@@ -94,7 +89,7 @@ impl Drawable for EnemyGphx {
             polygon([1.0, 1.0, 0.7, 0.1], &cone_polygon, transform_base, gl);
             */
 
-            image(texture, transform, gl);
+            self.animator.draw(&self.manager.animations, 0.0, &c.draw_state, transform, gl);
         });
     }
     fn set_position(&mut self, p: Pos) {
@@ -106,4 +101,10 @@ impl Drawable for EnemyGphx {
     fn should_draw(&self, _: &Rectangle) -> bool {
         true //TODO
     }
+    fn queue(&mut self, batch: &mut SpriteBatch, vt: &ViewTransform) -> bool {
+        if let Some(transform) = self.transform(vt, &batch.context()) {
+            self.animator.queue(&self.manager.animations, 0.0, transform, batch);
+        }
+        true
+    }
 }
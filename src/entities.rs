@@ -2,6 +2,7 @@ use collision::*;
 use draw::*;
 use game::*;
 use humanoid::*;
+use inventory::*;
 use logic::*;
 use physics::*;
 use piston::input::*;
@@ -73,22 +74,39 @@ impl Logical for PlayerColLogic {
 
 struct TriggerLogic {
     pub bb: BoundingBox,
+    pub trigger_id: TriggerId,
+    //  Item the player must be holding for the trigger to fire
+    pub requires: Option<String>,
+    pub inventory: Arc<Mutex<Inventory>>,
 }
 
 impl Logical for TriggerLogic {
     fn tick(&mut self, args: &LogicUpdateArgs) {
         let player_bb = args.world.get(args.world.player_id());
-        player_bb.map(|(_, pbb)| if self.bb.check_col(&pbb) {
-            args.metabuffer.issue(MetaCommand::Trigger(args.id));
+        player_bb.map(|(_, pbb)| if self.bb.check_col(&pbb) &&
+                                    has_required(&self.requires,
+                                                 &self.inventory) {
+            args.metabuffer.issue(MetaCommand::Trigger(self.trigger_id));
         });
     }
 }
 
+fn has_required(requires: &Option<String>,
+                inventory: &Arc<Mutex<Inventory>>)
+                -> bool {
+    match *requires {
+        Some(ref item) => inventory.lock().unwrap().has(item),
+        None => true,
+    }
+}
+
 pub fn create_trigger(id: Id,
-                      _trigger_id: Id,
+                      trigger_id: TriggerId,
                       pos: Pos,
                       width: Width,
                       height: Height,
+                      requires: Option<String>,
+                      inventory: Arc<Mutex<Inventory>>,
                       _world: &World)
                       -> GameObj {
     let g = arc_mut(GrphxNoDraw {});
@@ -99,10 +117,74 @@ pub fn create_trigger(id: Id,
             w: width,
             h: height,
         },
+        trigger_id: trigger_id,
+        requires: requires,
+        inventory: inventory,
     });
     GameObj::new(id, "trigger".to_owned(), g, p, l)
 }
 
+struct DoorLogic {
+    pub requires: Option<String>,
+    //  Use up the required item when opening
+    pub consume: bool,
+    pub inventory: Arc<Mutex<Inventory>>,
+}
+
+impl Logical for DoorLogic {
+    fn tick(&mut self, args: &LogicUpdateArgs) {
+        for m in args.message_buffer.read_buffer() {
+            let open = match m {
+                ObjMessage::MTrigger => true,
+                ObjMessage::MCollision(ref c) if c.other_type
+                    .contains(BBOwnerType::PLAYER) => {
+                    let mut inventory = self.inventory.lock().unwrap();
+                    match self.requires {
+                        Some(ref item) if self.consume => {
+                            inventory.remove(item, 1)
+                        }
+                        Some(ref item) => inventory.has(item),
+                        None => true,
+                    }
+                }
+                _ => false,
+            };
+            if open {
+                args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
+                return;
+            }
+        }
+    }
+}
+
+pub fn create_door(id: Id,
+                   pos: Pos,
+                   width: Width,
+                   height: Height,
+                   requires: Option<String>,
+                   consume: bool,
+                   inventory: Arc<Mutex<Inventory>>,
+                   world: &World)
+                   -> GameObj {
+    let g = arc_mut(GrphxRect {
+        pos: pos,
+        w: width,
+        h: height,
+        color: [0.4, 0.2, 0.1, 1.0],
+    });
+    let props = BBProperties {
+        id: id,
+        owner_type: BBOwnerType::BLOCK,
+    };
+    let p = arc_mut(PhysStatic::new(props, pos, width, height, world));
+    let l = arc_mut(DoorLogic {
+        requires: requires,
+        consume: consume,
+        inventory: inventory,
+    });
+    GameObj::new(id, "door".to_owned(), g, p, l)
+}
+
 struct DialogueLogic {
     pub text: String,
     pub triggered: bool,
@@ -137,24 +219,45 @@ pub fn create_dialogue(id: Id,
     GameObj::new(id, "dialogue".to_owned(), g, p, l)
 }
 
-pub fn create_crown(id: Id, pos: Pos, _world: &World) -> GameObj {
+pub fn create_crown(id: Id, pos: Pos, world: &World) -> GameObj {
+    create_pickup(id, ITEM_CROWN.to_owned(), 1, pos, world)
+}
+
+fn pickup_color(item: &str) -> Color {
+    match item {
+        ITEM_HEALTH => [1.0, 0.2, 0.2, 1.0],
+        ITEM_AMMO => [0.0, 0.5, 0.0, 1.0],
+        ITEM_CURRENCY => [1.0, 0.6, 0.0, 1.0],
+        ITEM_CROWN => [1.0, 1.0, 0.0, 1.0],
+        _ => [0.2, 0.2, 1.0, 1.0],
+    }
+}
+
+pub fn create_pickup(id: Id,
+                     item: String,
+                     amount: u32,
+                     pos: Pos,
+                     _world: &World)
+                     -> GameObj {
     let w = Width(32.0);
     let h = Height(32.0);
-    let c = [1.0, 1.0, 0.0, 1.0];
+    let c = pickup_color(&item);
     let g = arc_mut(GrphxRect {
         pos: pos,
         w: w,
         h: h,
         color: c,
     });
-    let crown_trigger =
-        Box::new(|args: &LogicUpdateArgs| {
+    let pickup_trigger =
+        Box::new(move |args: &LogicUpdateArgs| {
             args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
-            args.metabuffer.issue(MetaCommand::CollectCrown);
-            args.metabuffer.issue(MetaCommand::Dialogue(8, String::from("I am so good at this")));
+            args.metabuffer.issue(MetaCommand::Collect(item.clone(), amount));
+            if item == ITEM_CROWN {
+                args.metabuffer.issue(MetaCommand::Dialogue(8, String::from("I am so good at this")));
+            }
         });
-    let crown_update = Box::new(|args: &LogicUpdateArgs,
-                                 phys: Arc<Mutex<Physical>>| {
+    let pickup_update = Box::new(|args: &LogicUpdateArgs,
+                                  phys: Arc<Mutex<Physical>>| {
 
         let pos = phys.lock().unwrap().get_position();
         let Pos(_x, _y) = pos;
@@ -176,13 +279,13 @@ pub fn create_crown(id: Id, pos: Pos, _world: &World) -> GameObj {
 
     let bb = BoundingBox::new(pos, w, h);
     let (logic, p) =
-        PlayerColLogic::new_dyn(id, bb, crown_trigger, crown_update, g.clone());
+        PlayerColLogic::new_dyn(id, bb, pickup_trigger, pickup_update, g.clone());
     {
         let mut phys = p.lock().unwrap();
         phys.collide_with = BBOwnerType::BLOCK;
     }
     let l = arc_mut(logic);
-    GameObj::new(id, "crown".to_owned(), g, p, l)
+    GameObj::new(id, "pickup".to_owned(), g, p, l)
 }
 
 pub fn create_tinge(id: Id,
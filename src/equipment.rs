@@ -0,0 +1,129 @@
+/*
+ * Weapons held by the player
+ *
+ * Each slot tracks its own cooldown and ammo so switching weapons
+ * doesn't reset them
+ */
+
+use descriptors::WeaponDescriptor;
+use game::{Pos, fphys};
+use logic::LogicUpdateArgs;
+use weapons::Wieldable;
+
+pub struct EquipSlot {
+    pub weapon: Box<Wieldable>,
+    //  None for unlimited ammo
+    pub ammo: Option<u32>,
+    pub cd: fphys,
+}
+
+impl EquipSlot {
+    pub fn ready(&self) -> bool {
+        self.cd <= 0.0 && self.ammo != Some(0)
+    }
+}
+
+pub struct Equipment {
+    pub slots: Vec<EquipSlot>,
+    pub current: usize,
+    //  Time the trigger has been held for, None if not charging
+    pub charge: Option<fphys>,
+}
+
+impl Equipment {
+    pub fn new(weapons: &[WeaponDescriptor]) -> Self {
+        let slots = weapons.iter()
+            .map(|w| {
+                EquipSlot {
+                    weapon: w.weapon.to_wieldable(),
+                    ammo: w.ammo,
+                    cd: 0.0,
+                }
+            })
+            .collect::<Vec<EquipSlot>>();
+        Equipment {
+            slots: slots,
+            current: 0,
+            charge: None,
+        }
+    }
+
+    pub fn current_slot(&self) -> Option<&EquipSlot> {
+        self.slots.get(self.current)
+    }
+
+    pub fn next(&mut self) {
+        if !self.slots.is_empty() {
+            self.current = (self.current + 1) % self.slots.len();
+            self.charge = None;
+        }
+    }
+
+    pub fn prev(&mut self) {
+        if !self.slots.is_empty() {
+            self.current = (self.current + self.slots.len() - 1) %
+                           self.slots.len();
+            self.charge = None;
+        }
+    }
+
+    pub fn tick(&mut self, dt: fphys) {
+        for slot in &mut self.slots {
+            if slot.cd > 0.0 {
+                slot.cd -= dt;
+            }
+        }
+        let max_charge = self.current_slot()
+            .map(|s| s.weapon.max_charge())
+            .unwrap_or(0.0);
+        self.charge = self.charge.map(|c| (c + dt).min(max_charge));
+    }
+
+    pub fn start_charge(&mut self) {
+        let ready = self.current_slot().map(|s| s.ready()).unwrap_or(false);
+        if ready && self.charge.is_none() {
+            self.charge = Some(0.0);
+        }
+    }
+
+    //  Fire the current weapon if it was charging
+    //  Returns true if a shot was fired
+    pub fn release(&mut self,
+                   target: Pos,
+                   pos: Pos,
+                   args: &LogicUpdateArgs)
+                   -> bool {
+        let charge = match self.charge.take() {
+            Some(c) => c,
+            None => return false,
+        };
+        match self.slots.get_mut(self.current) {
+            Some(slot) => {
+                if !slot.ready() {
+                    return false;
+                }
+                slot.weapon.release(target, pos, charge, args);
+                slot.cd = slot.weapon.get_cd();
+                slot.ammo = slot.ammo.map(|a| a - 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    //  Ammo goes to the current weapon, or the first weapon that uses ammo
+    pub fn add_ammo(&mut self, amount: u32) {
+        let current_limited = self.current_slot()
+            .map(|s| s.ammo.is_some())
+            .unwrap_or(false);
+        let target = if current_limited {
+            Some(self.current)
+        } else {
+            self.slots.iter().position(|s| s.ammo.is_some())
+        };
+        if let Some(i) = target {
+            let slot = &mut self.slots[i];
+            slot.ammo = slot.ammo.map(|a| a + amount);
+        }
+    }
+}
@@ -4,6 +4,7 @@ extern crate glutin_window;
 extern crate opengl_graphics;
 extern crate rand;
 
+use atlas::atlas;
 use block::blocks_from_ghosts;
 use collision::Collision;
 use descriptors::*;
@@ -24,10 +25,14 @@ use physics::Physical;
 use piston::event_loop::*;
 use piston::input::*;
 use player::create as player_create;
-use shaders::NoisyShader;
+use player::PlayerLogic;
+use inventory::Inventory;
+use shaders::{NoisyShader, Program};
+use spawner::Director;
 use std::collections::HashMap;
+use std::fs;
 use std::ops::{Add, Mul, Sub};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{Receiver, Sender, channel};
@@ -35,6 +40,7 @@ use std::time::SystemTime;
 use tile::{Tile, TileManager};
 use world::World;
 use dyn::DynMap;
+use dyn::console::Console;
 use tools::{arc_mut};
 use ketos::Value;
 
@@ -109,6 +115,10 @@ pub enum ObjMessage {
     MPlayerStartGrapple(Pos),
     MPlayerEndGrapple,
     MTrigger,
+    MDamage(fphys),
+    //  Value sent from one script to another, with the sender's id
+    MScript(Id, Value),
+    MDescriptorsReloaded(Rc<ReloadedDescriptors>),
 }
 
 // Meta Commands
@@ -120,10 +130,16 @@ pub enum MetaCommand {
     MessageObject(Id, ObjMessage),
     ApplyForce(Id, Force),
     Dialogue(u32, String),
-    CollectCrown,
+    Collect(String, u32),
     Trigger(TriggerId),
     TingeY(fphys),
-    UpdateDynState(Id, Value),
+    //  Cameras owned by an object, keyed by the owner's id
+    AddCamera(Id, Box<Camera>),
+    RemoveCamera(Id),
+    //  Strength and duration in seconds
+    CameraShake(fphys, fphys),
+    CameraPunch(fphys, fphys),
+    BossStatus(Option<BossStatus>),
 }
 
 pub struct CommandBuffer<A> {
@@ -159,6 +175,8 @@ impl CommandBuffer<MetaCommand> {
 pub trait InputHandler {
     fn press(&mut self, button: Button);
     fn release(&mut self, button: Button);
+    //  Cursor position in world coordinates
+    fn move_cursor(&mut self, _pos: Pos) {}
 }
 
 // Load a 'descriptor' from a json filename
@@ -169,18 +187,86 @@ fn load_descriptor<T: Descriptor>(json_path: &Path) -> Rc<T> {
     match pd_r {
         Ok(s) => s,
         Err(e) => {
-            println!("Error loading descriptor!");
-            println!("{:?}", e.get_ref());
+            println!("Error loading descriptor {:?}", json_path);
+            println!("{}", e);
             println!("Crashing... :(");
             panic!();
         }
     }
 }
 
+//  Which descriptor a file under descriptors/ holds, if it is one the game reloads
+fn descriptor_kind(path: &Path) -> Option<&'static str> {
+    if path.parent().map_or(false, |p| p.ends_with("descriptors/enemy")) {
+        return Some("enemy");
+    }
+    match path.file_name().and_then(|f| f.to_str()) {
+        Some("world.json") => Some("world"),
+        Some("player.json") => Some("player"),
+        Some("grapple.json") => Some("grapple"),
+        _ => None,
+    }
+}
+
+//  Re-read descriptors changed on disk and hand them to every object
+//  A descriptor that fails to parse is reported and the old one kept
+fn reload_descriptors(game: &mut Noise, mut paths: Vec<PathBuf>) {
+    //  Any other json file may be a parent that descriptors extend
+    let parent_changed = paths.iter().any(|p| {
+        descriptor_kind(p).is_none() && p.extension().map_or(false, |e| e == "json")
+    });
+    if parent_changed {
+        paths = vec![PathBuf::from("descriptors/world.json"),
+                     PathBuf::from("descriptors/player.json"),
+                     PathBuf::from("descriptors/grapple.json")];
+        if let Ok(entries) = fs::read_dir("descriptors/enemy") {
+            paths.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+        }
+    }
+
+    let mut reloaded = ReloadedDescriptors::default();
+    for path in paths {
+        let result = match descriptor_kind(&path) {
+            Some("enemy") => {
+                EnemyDescriptor::new(&path).map(|d| {
+                    reloaded.enemies.insert(d.name.clone(), d);
+                })
+            }
+            Some("world") => WorldDescriptor::new(&path).map(|d| reloaded.world = Some(d)),
+            Some("player") => PlayerDescriptor::new(&path).map(|d| reloaded.player = Some(d)),
+            Some("grapple") => GrappleDescriptor::new(&path).map(|d| reloaded.grapple = Some(d)),
+            _ => continue,
+        };
+        match result {
+            Ok(()) => println!("Reloaded descriptor {:?}", path),
+            Err(e) => println!("Error reloading descriptor {:?}: {}", path, e),
+        }
+    }
+
+    if let Some(ref d) = reloaded.world {
+        game.world.descr = d.clone();
+    }
+    if let Some(ref d) = reloaded.player {
+        game.player_descriptor = d.clone();
+    }
+    if let Some(ref d) = reloaded.grapple {
+        game.grapple_descriptor = d.clone();
+    }
+    for (name, d) in &reloaded.enemies {
+        game.enemy_descriptors.insert(name.clone(), d.clone());
+    }
+
+    let reloaded = Rc::new(reloaded);
+    for o in &game.objs {
+        o.message_buffer.issue(ObjMessage::MDescriptorsReloaded(reloaded.clone()));
+    }
+}
+
 struct PlayerInfo {
     pub player_id: Id,
     pub grapple_id: Id,
     pub player_phys: Arc<Mutex<Physical>>,
+    pub player_logic: Arc<Mutex<PlayerLogic>>,
 }
 
 // Main game struct holding all information
@@ -197,11 +283,17 @@ struct Noise<'a> {
     pub overlay: Overlay,
     pub tile_manager: &'a TileManager,
     pub dialogue_buffer: DialogueBuffer,
-    pub tiles: Vec<Tile<'a>>,
+    pub tiles: Vec<Tile>,
     pub dyn_map: Arc<Mutex<DynMap>>,
+    pub inventory: Arc<Mutex<Inventory>>,
+    pub object_cameras: HashMap<Id, CameraId>,
+    pub director: Arc<Mutex<Director>>,
 }
 
-fn init_game<'a>(world_path: &Path, tile_manager: &'a TileManager) -> Noise<'a> {
+fn init_game<'a>(world_path: &Path,
+                 tile_manager: &'a TileManager,
+                 inventory: Arc<Mutex<Inventory>>)
+                 -> Noise<'a> {
     //  Create new world
     let world_descr = load_descriptor(Path::new("descriptors/world.json"));
     let mut world = World::new(world_descr);
@@ -230,7 +322,11 @@ fn init_game<'a>(world_path: &Path, tile_manager: &'a TileManager) -> Noise<'a>
 
     let metabuffer: CommandBuffer<MetaCommand> = CommandBuffer::new();
 
-    let dyn_map = DynMap::construct(world.id_gen.clone(), metabuffer.sender.clone());
+    let director = arc_mut(Director::new());
+
+    let dyn_map = DynMap::construct(world.id_gen.clone(),
+                                    metabuffer.sender.clone(),
+                                    inventory.clone());
 
     //  Load from json
     let poss_objs = from_json(world_path,
@@ -238,6 +334,8 @@ fn init_game<'a>(world_path: &Path, tile_manager: &'a TileManager) -> Noise<'a>
                                   grapple_obj,
                                   &enemy_descriptors,
                                   dyn_map.clone(),
+                                  inventory.clone(),
+                                  director.clone(),
                                   &mut world);
 
     let (objs, mut input_handlers, ghost_tiles) = poss_objs.unwrap();
@@ -260,6 +358,7 @@ fn init_game<'a>(world_path: &Path, tile_manager: &'a TileManager) -> Noise<'a>
         player_id: player_id,
         grapple_id: grapple_id,
         player_phys: player_phys,
+        player_logic: player_logic.clone(),
     };
 
     let dialogue_buffer = DialogueBuffer::new();
@@ -280,22 +379,34 @@ fn init_game<'a>(world_path: &Path, tile_manager: &'a TileManager) -> Noise<'a>
         tiles: tiles,
         overlay: overlay,
         dyn_map: dyn_map,
+        inventory: inventory,
+        object_cameras: HashMap::new(),
+        director: director,
     }
 }
 
+//  Stops after draw_stats_frames frames if given, printing what they cost
+//  to draw
 pub fn game_loop(world_path : &Path,
                  mut window: Window,
                  mut ctx: GlGraphics,
-                 mut shader: NoisyShader) {
+                 mut shader: NoisyShader,
+                 draw_stats_frames: Option<usize>) {
 
     let tile_manager = TileManager::load().unwrap();
-    let mut game = init_game(world_path, &tile_manager);
+    let inventory = arc_mut(Inventory::new());
+    let mut game = init_game(world_path, &tile_manager, inventory);
+    //  Kept outside the game so history survives restarts
+    let mut console = Console::new();
+    let mut batch = SpriteBatch::new();
+    let mut draw_profile = DrawProfile::new();
 
     game.dialogue_buffer
         .add(Dialogue::new(0.0, 10, String::from("")));
 
     let mut prev_time = SystemTime::now();
     let mut time = 0.0;
+    let mut view_size = (::SCREEN_WIDTH as fphys, ::SCREEN_HEIGHT as fphys);
 
     shader.set_following(game.player_info.player_id);
 
@@ -313,6 +424,18 @@ pub fn game_loop(world_path : &Path,
                 //  Update bounding box list
                 game.world.update();
 
+                //  Let scripts see where everything is this tick
+                {
+                    let mut dm = game.dyn_map.lock().unwrap();
+                    dm.update_world_view(&game.objs, &game.world);
+                }
+
+                {
+                    let player = game.player_info.player_logic.lock().unwrap();
+                    let mut director = game.director.lock().unwrap();
+                    director.update(u_args.dt, player.hp, player.hp_max);
+                }
+
                 let mut ids_remove: Vec<Id> = Vec::new();
                 let mut objects_add: Vec<GameObj> = Vec::new();
 
@@ -321,7 +444,8 @@ pub fn game_loop(world_path : &Path,
                 for c in meta_commands {
                     match c {
                         MetaCommand::RestartGame => {
-                            game = init_game(world_path, &tile_manager);
+                            let inventory = game.inventory.clone();
+                            game = init_game(world_path, &tile_manager, inventory);
                         }
                         MetaCommand::RemoveObject(id) => {
                             //println!("Destroy {}", id);
@@ -357,7 +481,14 @@ pub fn game_loop(world_path : &Path,
                                 text: t,
                             });
                         }
-                        MetaCommand::CollectCrown => {}
+                        MetaCommand::Collect(item, amount) => {
+                            let mut player = game.player_info
+                                .player_logic
+                                .lock()
+                                .unwrap();
+                            let mut inventory = game.inventory.lock().unwrap();
+                            inventory.collect(&item, amount, &mut player);
+                        }
                         MetaCommand::Trigger(trigger_id) => {
                             game.world
                                 .get_from_trigger_id(trigger_id)
@@ -374,9 +505,25 @@ pub fn game_loop(world_path : &Path,
                         MetaCommand::TingeY(y_target) => {
                             shader.set_color_morph_y_target(y_target);
                         }
-                        MetaCommand::UpdateDynState(id, state) => {
-                            let mut dm = game.dyn_map.lock().unwrap();
-                            dm.update_obj_state(&id, state);
+                        MetaCommand::AddCamera(id, camera) => {
+                            let camera_id = game.editor.add_camera(camera);
+                            if let Some(old) = game.object_cameras.insert(id, camera_id) {
+                                game.editor.remove_camera(old);
+                            }
+                        }
+                        MetaCommand::RemoveCamera(id) => {
+                            if let Some(camera_id) = game.object_cameras.remove(&id) {
+                                game.editor.remove_camera(camera_id);
+                            }
+                        }
+                        MetaCommand::CameraShake(intensity, duration) => {
+                            game.editor.shake(intensity, duration);
+                        }
+                        MetaCommand::CameraPunch(amount, duration) => {
+                            game.editor.punch_zoom(amount, duration);
+                        }
+                        MetaCommand::BossStatus(status) => {
+                            game.overlay.set_boss(status);
                         }
                     }
                 }
@@ -442,10 +589,17 @@ pub fn game_loop(world_path : &Path,
                 //  Update shader
                 shader.update(&game.world);
 
-                //  Check for updates to scripts
-                {
+                //  Decay camera shake and zoom effects
+                game.editor.tick(u_args.dt);
+
+                //  Check for updates to scripts and descriptors
+                let descriptor_changes = {
                     let mut dm = game.dyn_map.lock().unwrap();
                     dm.update();
+                    dm.take_descriptor_changes()
+                };
+                if !descriptor_changes.is_empty() {
+                    reload_descriptors(&mut game, descriptor_changes);
                 }
 
             }
@@ -462,6 +616,7 @@ pub fn game_loop(world_path : &Path,
                 draw_background(&r_args, &mut ctx);
 
                 let viewport = r_args.viewport().rect;
+                view_size = (viewport[2] as fphys, viewport[3] as fphys);
                 let view_transform = &game.editor
                     .transform();
 
@@ -475,30 +630,96 @@ pub fn game_loop(world_path : &Path,
                 .to_rectangle(viewport[2] as fphys,
                 viewport[3] as fphys);
 
-                shader.set_textured(&mut ctx);
+                batch.begin(&r_args);
+                batch.set_program(Program::Textured);
                 for tile in &mut game.tiles {
                     if tile.should_draw(view_rect) {
-                        tile.draw(&r_args, &mut ctx, &view_transform);
+                        tile.queue(&mut batch, &view_transform);
                     }
                 }
 
-                shader.set_colored(&mut ctx);
+                batch.set_program(Program::Colored);
                 for o in &game.objs {
                     //  Draw all objects
                     //  Currently no concept of depth
                     let mut gphx = o.draws.lock().unwrap();
-                    if gphx.should_draw(view_rect) {
+                    if gphx.should_draw(view_rect) &&
+                       !gphx.queue(&mut batch, &view_transform) {
+                        //  Draw what was queued first so this goes on top
+                        if batch.flush(&r_args, &mut ctx, &shader) {
+                            shader.set_colored(&mut ctx);
+                        }
                         gphx.draw(&r_args, &mut ctx, &view_transform);
+                        batch.stats.direct += 1;
                     }
                 }
+                batch.flush(&r_args, &mut ctx, &shader);
+                {
+                    //  Scripted objects only queue their drawing, draw it
+                    //  all at once so layers apply across objects
+                    let mut dm = game.dyn_map.lock().unwrap();
+                    batch.stats.direct +=
+                        dm.flush_draw(&r_args, &mut ctx, &view_transform, &shader);
+                }
+                {
+                    let dm = game.dyn_map.lock().unwrap();
+                    game.overlay.set_script_errors(dm.visible_script_errors());
+                }
                 if game.overlay.dialogue_empty() {
                     game.overlay.set_dialogue(game.dialogue_buffer.get(time));
                 }
                 game.overlay.draw(&r_args, &mut ctx, &view_transform);
+                console.draw(&r_args, &mut ctx, &view_transform);
+                batch.stats.direct += 2;
+
+                draw_profile.add(&batch.stats);
+                if draw_stats_frames.map_or(false, |n| draw_profile.frames() >= n) {
+                    if let Ok(atlas) = atlas() {
+                        draw_profile.set_atlas(atlas.sprite_count(), atlas.page_count());
+                    }
+                    println!("{}", draw_profile.report());
+                    break;
+                }
 
             }
 
+            Event::Input(Input::Text(text)) => {
+                if console.is_open() {
+                    console.type_text(&text);
+                }
+            }
+            Event::Input(Input::Move(Motion::MouseCursor(x, y))) => {
+                let (w, h) = view_size;
+                let pos = game.editor
+                    .transform()
+                    .screen_to_world(x, y, w, h);
+                for input_handler in &game.input_handlers {
+                    let mut ih = input_handler.lock().unwrap();
+                    ih.move_cursor(pos);
+                }
+            }
             Event::Input(Input::Button(b_args)) => {
+                if b_args.button == Button::Keyboard(Key::Backquote) {
+                    if b_args.state == ButtonState::Press {
+                        console.toggle();
+                    }
+                    continue;
+                }
+                if b_args.button == Button::Keyboard(Key::F1) {
+                    if b_args.state == ButtonState::Press {
+                        let dm = game.dyn_map.lock().unwrap();
+                        dm.dismiss_script_errors();
+                    }
+                    continue;
+                }
+                //  The console swallows input while it is open
+                if console.is_open() {
+                    if b_args.state == ButtonState::Press {
+                        let mut dm = game.dyn_map.lock().unwrap();
+                        console.press(b_args.button, &mut dm);
+                    }
+                    continue;
+                }
                 for input_handler in &game.input_handlers {
                     let mut ih = input_handler.lock().unwrap();
                     match b_args.state {
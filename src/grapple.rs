@@ -85,6 +85,14 @@ bitflags! {
 
 impl Logical for GrappleHolster {
     fn tick(&mut self, args: &LogicUpdateArgs) {
+        for m in args.message_buffer.read_buffer() {
+            if let ObjMessage::MDescriptorsReloaded(reloaded) = m {
+                if let Some(ref descr) = reloaded.grapple {
+                    self.descr = descr.clone();
+                    self.grapple.lock().unwrap().descr = descr.clone();
+                }
+            }
+        }
         let dt = args.piston.dt as fphys;
         if self.cd > 0.0 {
             self.cd -= dt;
@@ -0,0 +1,70 @@
+/*
+ * Items collected by the player
+ *
+ * The inventory outlives the game world so it is kept across restarts
+ */
+
+use game::fphys;
+use player::PlayerLogic;
+use std::collections::HashMap;
+
+pub const ITEM_HEALTH: &'static str = "health";
+pub const ITEM_AMMO: &'static str = "ammo";
+pub const ITEM_CURRENCY: &'static str = "currency";
+pub const ITEM_CROWN: &'static str = "crown";
+
+pub struct Inventory {
+    items: HashMap<String, u32>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Inventory { items: HashMap::new() }
+    }
+
+    pub fn count(&self, item: &str) -> u32 {
+        self.items.get(item).cloned().unwrap_or(0)
+    }
+
+    pub fn has(&self, item: &str) -> bool {
+        self.count(item) > 0
+    }
+
+    pub fn add(&mut self, item: &str, amount: u32) {
+        let count = self.items.entry(item.to_owned()).or_insert(0);
+        *count += amount;
+    }
+
+    //  Returns false without changing anything if there aren't enough
+    pub fn remove(&mut self, item: &str, amount: u32) -> bool {
+        let count = self.count(item);
+        if count < amount {
+            return false;
+        }
+        if count == amount {
+            self.items.remove(item);
+        } else {
+            self.items.insert(item.to_owned(), count - amount);
+        }
+        true
+    }
+
+    //  Health and ammo are applied to the player straight away,
+    //  everything else is stored
+    pub fn collect(&mut self,
+                   item: &str,
+                   amount: u32,
+                   player: &mut PlayerLogic) {
+        match item {
+            ITEM_HEALTH => {
+                player.hp = (player.hp + amount as fphys).min(player.hp_max);
+            }
+            ITEM_AMMO => {
+                player.equipment.add_ammo(amount);
+            }
+            _ => {
+                self.add(item, amount);
+            }
+        }
+    }
+}
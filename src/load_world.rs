@@ -1,16 +1,21 @@
 use block::*;
+use boss::create as boss_create;
 use descriptors::*;
 use enemy::create as enemy_create;
 
 use entities::*;
 use game::*;
 use gen::*;
-use draw::GrphxNoDraw;
+use inventory::Inventory;
+use spawner::{Director, SpawnerConfig, Wave};
+use spawner::create as spawner_create;
+use draw::{GrphxNoDraw, Rectangle};
 use physics::PhysNone;
-use dyn::{DynMap, make_dyn_obj};
+use dyn::{DynMap, json_to_lisp, make_dyn_obj};
 use dyn::logic::DynLogic;
 use dyn::graphics::{DynGraphics, ResourceContext};
 use rustc_serialize::json::{Array, Object};
+use schema;
 use rustc_serialize::json::Json;
 use std::collections::HashMap;
 
@@ -24,16 +29,6 @@ use std::sync::{Arc, Mutex};
 use tools::{arc_mut};
 
 
-fn get_array(dname: &str, obj: &Object, field: &str) -> Result<Array, Error> {
-    let raw = obj.get(field)
-        .ok_or(error_simple(dname,
-                            format!("has no field '{}'", field).as_str()))?;
-    let a = raw.as_array()
-        .ok_or(error_simple(dname,
-                            format!("'{}' is not an array", field).as_str()))?;
-    Ok(a.clone())
-}
-
 fn get_bool(dname: &str, obj: &Object, field: &str) -> Result<bool, Error> {
     //Ok(get_float(dname, obj, field)? > 0.0)
     let raw = obj.get(field)
@@ -44,11 +39,56 @@ fn get_bool(dname: &str, obj: &Object, field: &str) -> Result<bool, Error> {
                             format!("'{}' is not a boolean", field).as_str()))
 }
 
+fn get_optional_string(dname: &str,
+                       obj: &Object,
+                       field: &str)
+                       -> Result<Option<String>, Error> {
+    if obj.contains_key(field) {
+        Ok(Some(get_string(dname, obj, field)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn get_waves(obj: &Object) -> Result<Vec<Wave>, Error> {
+    let mut waves = Vec::new();
+    if !obj.contains_key("waves") {
+        return Ok(waves);
+    }
+    for (i, poss_wave) in get_array("spawner", obj, "waves")?
+        .iter()
+        .enumerate() {
+        let wave_obj = poss_wave.as_object()
+            .ok_or(error_simple("spawner",
+                                format!("wave {} not well formed", i)
+                                    .as_str()))?;
+        waves.push(Wave {
+            count: get_number("spawner", wave_obj, "count")? as u32,
+            interval: get_float("spawner", wave_obj, "interval")?,
+            delay: get_float("spawner", wave_obj, "delay")?,
+        });
+    }
+    Ok(waves)
+}
+
+//  The "fields" a world object overrides its descriptor with, if any
+fn get_overrides<'a>(dname: &str, obj: &'a Object) -> Result<Option<&'a Object>, Error> {
+    match obj.get("fields") {
+        Some(fields) => {
+            Ok(Some(fields.as_object()
+                .ok_or(error_simple(dname, "fields is not an object"))?))
+        }
+        None => Ok(None),
+    }
+}
+
 pub fn from_json(path: &Path,
                  player: GameObj,
                  grapple: GameObj,
-                 _enemy_descriptors: &HashMap<String, Rc<EnemyDescriptor>>,
+                 enemy_descriptors: &HashMap<String, Rc<EnemyDescriptor>>,
                  dyn_map: Arc<Mutex<DynMap>>,
+                 inventory: Arc<Mutex<Inventory>>,
+                 director: Arc<Mutex<Director>>,
                  world: &mut World)
                  -> Result<(Vec<GameObj>, Vec<Arc<Mutex<InputHandler>>>, Vec<GhostTile>), Error> {
     let mut gobjs = Vec::new();
@@ -64,6 +104,7 @@ pub fn from_json(path: &Path,
     gobjs.push(player);
 
     let json_world = load_json("world", path)?;
+    check_schema("world", &schema::WORLD_FILE, &json_world)?;
     let world_objs = get_array("world", &json_world, "world")?;
     for (i, poss_obj) in world_objs.iter().enumerate() {
         let obj =
@@ -85,7 +126,6 @@ pub fn from_json(path: &Path,
                 p.set_position(Pos(x, y));
             }
             "enemy" | "blue_enemy" | "red_enemy" => {
-              /*
                 let faction = get_number("world", obj, "allegiance")? as u32;
                 let descriptor_name =
                     get_string("world", obj, "descriptor")?.to_string();
@@ -94,18 +134,29 @@ pub fn from_json(path: &Path,
                                                       descriptor {}",
                                                      &descriptor_name)
                                                  .as_str());
-                let descr = enemy_descriptors.get(&descriptor_name)
+                let mut descr = enemy_descriptors.get(&descriptor_name)
                     .ok_or(descr_err)?
                     .clone();
+                if let Some(fields) = get_overrides("world", obj)? {
+                    descr = EnemyDescriptor::with_overrides(&descr, fields)?;
+                }
                 let e = enemy_create(id, pos, descr, &world, faction);
                 gobjs.push(e);
-                */
             }
             "dyn" => {
                 let logic_name =
                     get_string("world", obj, "script")?.to_string();
 
-                let (gobj, am_dl) = make_dyn_obj(id, &dyn_map, &resource_context, &logic_name);
+                let (gobj, am_dl) = make_dyn_obj(id, &dyn_map, &resource_context, &logic_name, pos);
+                if let Some(fields) = obj.get("fields") {
+                    let mut dm = dyn_map.lock().unwrap();
+                    dm.set_init_args(id, json_to_lisp(fields));
+                }
+                if obj.contains_key("connect_id") {
+                    let trigger_id = get_number("dyn", obj, "connect_id")? as
+                                     TriggerId;
+                    world.add_to_trigger_id_map(trigger_id, id);
+                }
                 gobjs.push(gobj);
                 input_handlers.push(am_dl as Arc<Mutex<InputHandler>>);
             }
@@ -151,10 +202,47 @@ pub fn from_json(path: &Path,
                 let c = create_crown(id, pos, &world);
                 gobjs.push(c);
             }
+            "pickup" => {
+                let item = get_string("pickup", obj, "item")?;
+                let amount = get_number("pickup", obj, "amount")? as u32;
+                let c = create_pickup(id, item, amount, pos, &world);
+                gobjs.push(c);
+            }
+            "door" => {
+                let requires = get_optional_string("door", obj, "requires")?;
+                let consume = if obj.contains_key("consume") {
+                    get_bool("door", obj, "consume")?
+                } else {
+                    false
+                };
+                if obj.contains_key("connect_id") {
+                    let trigger_id = get_number("door", obj, "connect_id")? as
+                                     TriggerId;
+                    world.add_to_trigger_id_map(trigger_id, id);
+                }
+                let c = create_door(id,
+                                    pos,
+                                    w,
+                                    h,
+                                    requires,
+                                    consume,
+                                    inventory.clone(),
+                                    &world);
+                gobjs.push(c);
+            }
             "trigger" => {
                 let trigger_id =
-                    get_number("trigger", obj, "connect_target_id")? as Id;
-                let c = create_trigger(id, trigger_id, pos, w, h, &world);
+                    get_number("trigger", obj, "connect_target_id")? as
+                    TriggerId;
+                let requires = get_optional_string("trigger", obj, "requires")?;
+                let c = create_trigger(id,
+                                       trigger_id,
+                                       pos,
+                                       w,
+                                       h,
+                                       requires,
+                                       inventory.clone(),
+                                       &world);
                 gobjs.push(c);
             }
             "dialogue" => {
@@ -165,6 +253,61 @@ pub fn from_json(path: &Path,
                 world.add_to_trigger_id_map(trigger_id, id);
                 gobjs.push(c);
             }
+            "boss" => {
+                let descr_path = get_string("boss", obj, "descriptor")?;
+                let mut descr = BossDescriptor::new(Path::new(&descr_path))?;
+                if let Some(fields) = get_overrides("boss", obj)? {
+                    descr = BossDescriptor::with_overrides(&descr, fields)?;
+                }
+                let arena = Rectangle::new(get_float("boss", obj, "arena_x")?,
+                                           get_float("boss", obj, "arena_y")?,
+                                           get_float("boss", obj, "arena_width")?,
+                                           get_float("boss",
+                                                     obj,
+                                                     "arena_height")?);
+                let trigger_id = get_number("boss", obj, "connect_id")? as
+                                 TriggerId;
+                world.add_to_trigger_id_map(trigger_id, id);
+                let b = boss_create(id, pos, arena, descr, world.descr.clone());
+                gobjs.push(b);
+            }
+            "spawner" => {
+                let descriptor_name = get_string("spawner", obj, "descriptor")?;
+                let descr_err = error_simple("spawner",
+                                             format!("Could not find enemy \
+                                                      descriptor {}",
+                                                     &descriptor_name)
+                                                 .as_str());
+                let mut descr = enemy_descriptors.get(&descriptor_name)
+                    .ok_or(descr_err)?
+                    .clone();
+                //  Spawned enemies can override any descriptor field
+                if let Some(fields) = get_overrides("spawner", obj)? {
+                    descr = EnemyDescriptor::with_overrides(&descr, fields)?;
+                }
+                let spawn_chance = if obj.contains_key("spawn_chance") {
+                    get_float("spawner", obj, "spawn_chance")?
+                } else {
+                    ENEMY_GEN_P
+                };
+                let on_trigger = obj.contains_key("connect_id");
+                if on_trigger {
+                    let trigger_id = get_number("spawner", obj, "connect_id")? as
+                                     TriggerId;
+                    world.add_to_trigger_id_map(trigger_id, id);
+                }
+                let config = SpawnerConfig {
+                    descr: descr,
+                    faction: get_number("spawner", obj, "allegiance")? as u32,
+                    width: w,
+                    spawn_chance: spawn_chance,
+                    max_alive: get_number("spawner", obj, "max_alive")? as u32,
+                    waves: get_waves(obj)?,
+                    on_trigger: on_trigger,
+                };
+                let s = spawner_create(id, pos, config, director.clone());
+                gobjs.push(s);
+            }
             "tinge" => {
                 let yy = 3.141 / 2.0;
                 //let yy = get_float("tinge", obj, "y_angle")?;
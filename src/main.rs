@@ -12,6 +12,7 @@ extern crate rustc_serialize;
 extern crate find_folder;
 extern crate rayon;
 extern crate notify;
+extern crate image;
 #[macro_use]
 extern crate ketos;
 #[macro_use]
@@ -22,11 +23,16 @@ use opengl_graphics::{Colored, GLSL, GlGraphics, OpenGL, Shaders, Textured};
 use opengl_graphics::shader_uniforms::*;
 use piston::window::WindowSettings;
 use std::env;
+use std::process;
 use std::path::Path;
 
+#[allow(unused_imports)]
+mod ballistics;
 #[allow(unused_imports)]
 mod block;
 #[allow(unused_imports)]
+mod boss;
+#[allow(unused_imports)]
 mod collision;
 #[allow(unused_imports)]
 mod descriptors;
@@ -51,6 +57,8 @@ mod player_graphics;
 #[allow(unused_imports)]
 mod shaders;
 #[allow(unused_imports)]
+mod spawner;
+#[allow(unused_imports)]
 mod tile;
 #[allow(unused_imports)]
 mod tools;
@@ -63,14 +71,26 @@ mod load_world;
 #[allow(unused_imports)]
 mod enemy_graphics;
 #[allow(unused_imports)]
+mod equipment;
+#[allow(unused_imports)]
 mod entities;
 #[allow(unused_imports)]
 mod overlay;
 #[allow(unused_imports)]
 mod weapons;
 #[allow(unused_imports)]
+mod schema;
+#[allow(unused_imports)]
+mod sprite_sheet;
+#[allow(unused_imports)]
+mod animator;
+#[allow(unused_imports)]
+mod atlas;
+#[allow(unused_imports)]
 mod humanoid;
 #[allow(unused_imports)]
+mod inventory;
+#[allow(unused_imports)]
 mod dyn;
 
 use game::game_loop;
@@ -85,6 +105,26 @@ pub const SCREEN_HEIGHT: u32 = 540;
 fn main() {
     let args : Vec<String> = env::args().collect();
     
+    //  Run the script tests instead of the game, see dyn/harness.rs
+    if args.len() >= 2 && args[1] == "--test-scripts" {
+        let dir = args.get(2).map(|x| x.as_str()).unwrap_or("scripts/tests");
+        let passed = dyn::harness::run_tests(Path::new(dir));
+        process::exit(if passed { 0 } else { 1 });
+    }
+
+    //  Draw this many frames then print their draw call counts and exit
+    let (draw_stats_frames, args) = if args.len() >= 3 && args[1] == "--draw-stats" {
+        let frames = args[2].parse::<usize>().unwrap_or_else(|_| {
+            println!("--draw-stats takes a number of frames");
+            process::exit(1);
+        });
+        let mut rest = vec![args[0].clone()];
+        rest.extend(args[3..].iter().cloned());
+        (Some(frames), rest)
+    } else {
+        (None, args)
+    };
+
     let world_filename = match args.len() {
       2 => {
           args[1].clone()
@@ -167,5 +207,5 @@ fn main() {
 
     println!("Starting");
 
-    game_loop(world_path, window, context, shader);
+    game_loop(world_path, window, context, shader, draw_stats_frames);
 }
@@ -3,6 +3,7 @@ extern crate gl;
 
 use self::gl::types::GLuint;
 use draw::*;
+use dyn::errors::ScriptError;
 use game::{Pos, fphys};
 use graphics::character::CharacterCache;
 use graphics::text::Text;
@@ -16,6 +17,13 @@ use player::PlayerLogic;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+//  Health of the boss currently being fought
+pub struct BossStatus {
+    pub name: String,
+    pub hp: fphys,
+    pub hp_max: fphys,
+}
+
 pub struct Overlay {
     player: Arc<Mutex<PlayerLogic>>,
     hpbar_h: fphys,
@@ -31,8 +39,15 @@ pub struct Overlay {
     dialogue: String,
     dialogue_time_left: u32,
     dialogue_chars: usize,
+    boss: Option<BossStatus>,
+    script_errors: Vec<ScriptError>,
 }
 
+//  Most script errors listed in the panel at once
+const MAX_ERROR_LINES: usize = 8;
+const ERROR_LINE_HEIGHT: fphys = 20.0;
+const ERROR_CHAR_SIZE: u32 = 16;
+
 impl Overlay {
     pub fn new(player: Arc<Mutex<PlayerLogic>>) -> Self {
         const COLOR: Color = [1.0, 1.0, 1.0, 1.0];
@@ -57,6 +72,8 @@ impl Overlay {
             dialogue: String::new(),
             dialogue_time_left: 1,
             dialogue_chars: 0,
+            boss: None,
+            script_errors: Vec::new(),
         }
     }
     pub fn dialogue_empty(&mut self) -> bool {
@@ -74,6 +91,14 @@ impl Overlay {
             self.dialogue_chars = 0
         });
     }
+
+    pub fn set_boss(&mut self, status: Option<BossStatus>) {
+        self.boss = status;
+    }
+
+    pub fn set_script_errors(&mut self, errors: Vec<ScriptError>) {
+        self.script_errors = errors;
+    }
 }
 
 impl Drawable for Overlay {
@@ -85,6 +110,7 @@ impl Drawable for Overlay {
         let hp;
         let hp_max;
         const BLACK: Color = [0.0, 0.0, 0.0, 1.0];
+        const BOSS_BAR: Color = [0.8, 0.1, 0.1, 1.0];
         {
             let p = self.player.lock().unwrap();
             hp = p.hp;
@@ -116,6 +142,32 @@ impl Drawable for Overlay {
                     .scale(1.0, self.blackbar_percent);
                 rectangle(BLACK, letterbox_up, transform_bot, gl);
 
+                //  Boss hp bar sits in the top letterbox
+                if let Some(ref boss) = self.boss {
+                    let bar_w = 0.6 * vp.rect[2] as f64;
+                    let bar_x = 0.2 * vp.rect[2] as f64;
+                    let bar_y = 0.5 * self.blackbar_percent * vp.rect[3] as f64;
+                    let frac = (boss.hp / boss.hp_max).max(0.0);
+                    rectangle(self.hpbar_border_c,
+                              [bar_x - self.border,
+                               bar_y - self.border,
+                               bar_w + 2.0 * self.border,
+                               self.hpbar_h + 2.0 * self.border],
+                              c.transform,
+                              gl);
+                    rectangle(BOSS_BAR,
+                              [bar_x, bar_y, bar_w * frac, self.hpbar_h],
+                              c.transform,
+                              gl);
+                    let transform_name = c.transform
+                        .trans(bar_x, bar_y - 2.0 * self.border);
+                    self.text.draw(boss.name.as_str(),
+                                   &mut self.char_cache,
+                                   &c.draw_state,
+                                   transform_name,
+                                   gl);
+                }
+
                 if self.dialogue_time_left > 1 {
                     let dc = if self.dialogue_chars > self.dialogue.len() {
                         self.dialogue.len()
@@ -134,6 +186,51 @@ impl Drawable for Overlay {
                                    transform_text,
                                    gl);
                 }
+
+                //  Script errors, dismissed with F1
+                if !self.script_errors.is_empty() {
+                    const ERROR_BG: Color = [0.3, 0.0, 0.0, 0.85];
+                    const ERROR_TEXT: Color = [1.0, 0.8, 0.8, 1.0];
+                    let shown = self.script_errors.len().min(MAX_ERROR_LINES);
+                    let mut lines = self.script_errors[..shown]
+                        .iter()
+                        .map(|e| {
+                            let location = match e.line {
+                                Some(line) => format!("{}:{}", e.script, line),
+                                None => e.script.clone(),
+                            };
+                            let first = e.message.lines().next().unwrap_or("");
+                            if e.count > 1 {
+                                format!("{} {} (x{})", location, first, e.count)
+                            } else {
+                                format!("{} {}", location, first)
+                            }
+                        })
+                        .collect::<Vec<String>>();
+                    if self.script_errors.len() > shown {
+                        lines.push(format!("... and {} more",
+                                           self.script_errors.len() - shown));
+                    }
+                    lines.push("F1 to dismiss".to_owned());
+
+                    let top = self.blackbar_percent * vp.rect[3] as f64;
+                    let panel_h = ERROR_LINE_HEIGHT * (lines.len() as fphys + 0.5);
+                    rectangle(ERROR_BG,
+                              [0.0, top, vp.rect[2] as f64, panel_h],
+                              c.transform,
+                              gl);
+                    let mut text = Text::new(ERROR_CHAR_SIZE);
+                    text.color = ERROR_TEXT;
+                    for (i, line) in lines.iter().enumerate() {
+                        let transform_line = c.transform
+                            .trans(4.0, top + (i + 1) as fphys * ERROR_LINE_HEIGHT);
+                        let _ = text.draw(line.as_str(),
+                                          &mut self.char_cache,
+                                          &c.draw_state,
+                                          transform_line,
+                                          gl);
+                    }
+                }
             });
         });
     }
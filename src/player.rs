@@ -1,14 +1,15 @@
+use animator::Animator;
 use collision::*;
 use descriptors::*;
 use dialogue::Dialogue;
 use draw::{Drawable, GrphxRect};
+use equipment::Equipment;
 use game::*;
 use humanoid::*;
 use logic::*;
 use opengl_graphics::Texture;
 use physics::{PhysDyn, Physical};
 use piston::input::*;
-use ketos::Value;
 
 use player_graphics::*;
 
@@ -28,17 +29,14 @@ pub struct PlayerLogic {
     world_descr: Rc<WorldDescriptor>,
     grappling: bool,
     grapple_target: Option<Pos>,
+    pub equipment: Equipment,
+    cursor: Pos,
+    release_queued: bool,
+    fire_anim: fphys,
     pub hp: fphys,
     pub hp_max: fphys,
 }
 
-
-#[derive(Clone, Debug, ForeignValue, FromValueClone, StructValue)]
-pub struct PlayerDynState {
-    x : fphys,
-    y : fphys,
-}
-
 impl PlayerLogic {
     pub fn new(draw: Arc<Mutex<PlayerGphx>>,
                descr: Rc<PlayerDescriptor>,
@@ -55,6 +53,10 @@ impl PlayerLogic {
             collision_buffer: Vec::new(),
             grappling: false,
             grapple_target: None,
+            equipment: Equipment::new(&descr.weapons),
+            cursor: Pos(0.0, 0.0),
+            release_queued: false,
+            fire_anim: 0.0,
             hp: descr.start_hp,
             hp_max: descr.start_hp,
             cds: Cooldowns::new(),
@@ -72,7 +74,7 @@ impl Logical for PlayerLogic {
         let phys_info = get_phys_info(self.physics.clone());
         let Pos(x, y) = phys_info.pos;
         let Vel(xvel, yvel) = phys_info.vel;
-        let _dt = args.piston.dt as fphys;
+        let dt = args.piston.dt as fphys;
 
         // Conditions for player death
         if self.hp < 0.0 || y > MAX_HEIGHT {
@@ -94,6 +96,23 @@ impl Logical for PlayerLogic {
                     self.grappling = false;
                     self.grapple_target = None;
                 }
+                ObjMessage::MDamage(dmg) => {
+                    if self.cds.hit <= 0.0 {
+                        self.cds.hit = self.descr.damage_cd;
+                        self.hp -= dmg;
+                    }
+                }
+                ObjMessage::MDescriptorsReloaded(reloaded) => {
+                    if let Some(ref descr) = reloaded.player {
+                        self.descr = descr.clone();
+                        let mut d = self.draw.lock().unwrap();
+                        d.manager = descr.clone();
+                        d.animator.set_descriptor(descr.animator.clone());
+                    }
+                    if let Some(ref world_descr) = reloaded.world {
+                        self.world_descr = world_descr.clone();
+                    }
+                }
                 _ => {}
             }
         }
@@ -121,6 +140,20 @@ impl Logical for PlayerLogic {
         //  Reset collisions
         self.collision_buffer = Vec::new();
 
+        //  Weapons
+        self.equipment.tick(args.piston.dt);
+        if self.fire_anim > 0.0 {
+            self.fire_anim -= args.piston.dt;
+        }
+        if self.release_queued {
+            self.release_queued = false;
+            let centre = Pos(x + phys_info.w.0 / 2.0, y + phys_info.h.0 / 2.0);
+            if self.equipment.release(self.cursor, centre, args) {
+                self.fire_anim = self.descr.fire_anim_time;
+            }
+        }
+        let aiming = self.equipment.charge.is_some();
+
         let mut on_ground = false;
         {
             let phys = self.physics.lock().unwrap();
@@ -128,42 +161,25 @@ impl Logical for PlayerLogic {
         }
         {
             let mut d = self.draw.lock().unwrap();
-            //  Set draw state
-            let (draw_state, draw_speed_mod, draw_angle) =
-                if self.cds.dash > 0.0 {
-                    (PlayerDrawState::Dash, 1.0, 0.0)
-                    /*
-            } else if self.grappling {
-                let angle = match self.grapple_target {
-                    Some((gx, gy)) => {
-                        let dx = gx - x;
-                        let dy = gy - y;
-                        dy.atan2(dx)
-                    }
-                    None => {0.0}
-                };
-                (PlayerDrawState::Swing, 1.0, angle)
-                */
-                } else if !on_ground {
-                    if yvel < 0.0 {
-                        (PlayerDrawState::Jump, 1.0, 0.0)
-                    } else {
-                        (PlayerDrawState::Fall, 1.0, 0.0)
-                    }
-                } else if xvel.abs() > 3.0 {
-                    let sm = xvel.abs() / self.descr.max_runspeed;
-                    (PlayerDrawState::Run, (sm.sqrt()) + 0.5, 0.0)
-                } else {
-                    (PlayerDrawState::Idle, 1.0, 0.0)
-                };
-            d.state = draw_state;
-            d.speed_mod = draw_speed_mod;
-            d.angle = draw_angle;
+            //  Parameters for the animator in player.json
+            d.animator.set_bool("on_ground", on_ground);
+            d.animator.set_bool("dashing", self.cds.dash > 0.0);
+            d.animator.set_bool("firing", self.fire_anim > 0.0);
+            d.animator.set_bool("aiming", aiming);
+            d.animator.set_bool("grappling", self.grappling);
+            d.animator.set("weapon", self.equipment.current as fphys);
+            d.animator.set("speed", xvel.abs());
+            d.animator.set("yvel", yvel);
+            d.animator.set("run_rate", (xvel.abs() / self.descr.max_runspeed).sqrt() + 0.5);
+            //  Nothing listens for the player's animation events yet
+            d.animator.update(dt, &self.descr.animations);
 
-            if xvel > 1.0 {
+            if aiming || self.fire_anim > 0.0 {
+                //  Face the cursor while using a weapon
+                d.reverse = self.cursor.0 < x;
+            } else if xvel > 1.0 {
                 d.reverse = false;
-            }
-            if xvel < -1.0 {
+            } else if xvel < -1.0 {
                 d.reverse = true;
             }
         }
@@ -178,8 +194,7 @@ impl Logical for PlayerLogic {
                        &self.descr.to_move_descr(self.world_descr.clone()),
                        self.physics.clone());
 
-        let dyn_state = PlayerDynState {x, y};
-        args.metabuffer.issue(MetaCommand::UpdateDynState(args.id, Value::Foreign(Rc::new(dyn_state))));
+        args.world.report_health(args.id, self.hp, self.hp_max);
     }
 }
 
@@ -201,6 +216,15 @@ impl InputHandler for PlayerLogic {
             Button::Keyboard(Key::Space) => {
                 self.input |= HumanoidInput::DASH;
             }
+            Button::Keyboard(Key::Q) => {
+                self.equipment.prev();
+            }
+            Button::Keyboard(Key::E) => {
+                self.equipment.next();
+            }
+            Button::Mouse(MouseButton::Left) => {
+                self.equipment.start_charge();
+            }
             _ => {}
         }
     }
@@ -221,9 +245,15 @@ impl InputHandler for PlayerLogic {
             Button::Keyboard(Key::Space) => {
                 self.input &= !HumanoidInput::DASH;
             }
+            Button::Mouse(MouseButton::Left) => {
+                self.release_queued = true;
+            }
             _ => {}
         }
     }
+    fn move_cursor(&mut self, pos: Pos) {
+        self.cursor = pos;
+    }
 }
 
 pub fn create(id: Id,
@@ -239,12 +269,9 @@ pub fn create(id: Id,
         pos: pos,
         angle: 0.0,
         scale: descr.scale,
-        speed: descr.speed,
-        speed_mod: 1.0,
-        state: PlayerDrawState::Idle,
+        animator: Animator::new(descr.animator.clone()),
         reverse: false,
         manager: descr.clone(),
-        frame: 1.0,
     };
 
     let g = arc_mut(graphics);
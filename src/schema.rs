@@ -0,0 +1,846 @@
+use rustc_serialize::json::{Json, Object};
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/*
+ * Schemas describing every descriptor and world file
+ *
+ * Files are checked against these before being loaded so that all problems
+ * are reported together, each with the json path it was found at.
+ * Fields starting with '_' are treated as comments and never checked.
+ *
+ * This module only depends on rustc_serialize so that the noise-lint binary
+ * can include it without pulling in the rest of the game.
+ */
+
+pub enum Kind {
+    Number,
+    //  A whole number that isn't negative
+    Count,
+    Bool,
+    Str,
+    OneOf(&'static [&'static str]),
+    //  A path to a file that must exist
+    File,
+    //  The name of a script in scripts/
+    Script,
+    Object(&'static Schema),
+    ArrayOf(&'static Kind),
+    //  An object with any keys, each value being kind
+    MapOf(&'static Kind),
+    //  An object checked against the schema named by one of its fields
+    Tagged(&'static str, &'static [&'static Schema]),
+    //  Anything, eg. fields passed through to a script
+    Any,
+}
+
+#[derive(PartialEq)]
+pub enum Need {
+    Required,
+    Optional,
+}
+
+use self::Kind::*;
+use self::Need::*;
+
+pub struct Field(pub &'static str, pub Kind, pub Need);
+
+pub struct Schema {
+    pub name: &'static str,
+    //  Fields shared with other schemas
+    pub base: Option<&'static Schema>,
+    pub fields: &'static [Field],
+    //  Animations given as <name>_frames and <name>_path,
+    //  each frame must exist as <path><frame>.png
+    //  An empty name is for fields without the prefix, ie. frames and path
+    pub sprites: &'static [&'static str],
+    //  (field, value, needed) where needed is required if field is value
+    pub conditional: &'static [(&'static str, &'static str, &'static str)],
+}
+
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Kind {
+    fn describe(&self) -> String {
+        match *self {
+            Number => "a number".to_owned(),
+            Count => "a whole number".to_owned(),
+            Bool => "true or false".to_owned(),
+            Str => "a string".to_owned(),
+            OneOf(options) => format!("one of {}", options.join(", ")),
+            File => "a path to an existing file".to_owned(),
+            Script => "the name of a script in scripts/".to_owned(),
+            Object(schema) => format!("a {} object", schema.name),
+            ArrayOf(kind) => format!("an array of {}", kind.describe()),
+            MapOf(kind) => format!("an object of {}", kind.describe()),
+            Tagged(tag, _) => format!("an object with a '{}'", tag),
+            Any => "anything".to_owned(),
+        }
+    }
+}
+
+fn json_type(value: &Json) -> &'static str {
+    match *value {
+        Json::I64(_) | Json::U64(_) | Json::F64(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Boolean(_) => "a boolean",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+        Json::Null => "null",
+    }
+}
+
+//  Fields of overrides replace those of base
+pub fn merge_fields(base: &Object, overrides: &Object) -> Object {
+    let mut merged = base.clone();
+    for (field, value) in overrides {
+        merged.insert(field.clone(), value.clone());
+    }
+    merged
+}
+
+pub fn read_json(json_path: &Path) -> Result<Object, String> {
+    let mut s = String::new();
+    File::open(json_path)
+        .and_then(|mut f| f.read_to_string(&mut s))
+        .map_err(|e| format!("could not read {:?}: {}", json_path, e))?;
+    let data = Json::from_str(s.as_str())
+        .map_err(|e| format!("{:?} is not well-formed json: {}", json_path, e))?;
+    match data {
+        Json::Object(o) => Ok(o),
+        _ => Err(format!("{:?} is not a json object", json_path)),
+    }
+}
+
+//  Read a descriptor, following "extends" through the files it inherits from
+//  Paths in "extends" are relative to the game directory like all others
+pub fn load_resolved(json_path: &Path) -> Result<Object, String> {
+    load_extended(json_path, &mut Vec::new())
+}
+
+fn load_extended(json_path: &Path, chain: &mut Vec<PathBuf>) -> Result<Object, String> {
+    if chain.iter().any(|p| p == json_path) {
+        return Err(format!("{:?} extends itself", json_path));
+    }
+    chain.push(json_path.to_path_buf());
+    let mut obj = read_json(json_path)?;
+    match obj.remove("extends") {
+        Some(Json::String(parent_path)) => {
+            let parent = load_extended(Path::new(&parent_path), chain)?;
+            Ok(merge_fields(&parent, &obj))
+        }
+        Some(_) => Err(format!("{:?}: $.extends is not a string", json_path)),
+        None => Ok(obj),
+    }
+}
+
+//  Every problem with obj, an empty list if it matches the schema
+pub fn validate(schema: &Schema, obj: &Object) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    check_object(schema, obj, "$", &mut errors);
+    errors
+}
+
+fn error(errors: &mut Vec<SchemaError>, path: &str, message: String) {
+    errors.push(SchemaError {
+        path: path.to_owned(),
+        message: message,
+    });
+}
+
+fn schema_fields(schema: &Schema) -> Vec<&Field> {
+    let mut fields = schema.base.map_or(Vec::new(), schema_fields);
+    fields.extend(schema.fields.iter());
+    fields
+}
+
+fn check_object(schema: &Schema,
+                obj: &Object,
+                path: &str,
+                errors: &mut Vec<SchemaError>) {
+    let fields = schema_fields(schema);
+    for &&Field(name, ref kind, ref need) in &fields {
+        match obj.get(name) {
+            Some(value) => {
+                check_value(kind, value, &format!("{}.{}", path, name), errors)
+            }
+            None if *need == Required => {
+                error(errors,
+                      path,
+                      format!("missing field '{}', expected {}",
+                              name,
+                              kind.describe()))
+            }
+            None => {}
+        }
+    }
+
+    for key in obj.keys().filter(|k| !k.starts_with('_')) {
+        if fields.iter().any(|f| f.0 == key.as_str()) {
+            continue;
+        }
+        let suggestion = fields.iter()
+            .map(|f| (f.0, edit_distance(key, f.0)))
+            .filter(|&(_, d)| d <= 2)
+            .min_by_key(|&(_, d)| d);
+        let message = match suggestion {
+            Some((name, _)) => {
+                format!("unknown field '{}' in {}, did you mean '{}'?",
+                        key,
+                        schema.name,
+                        name)
+            }
+            None => format!("unknown field '{}' in {}", key, schema.name),
+        };
+        error(errors, path, message);
+    }
+
+    for &(field, value, needed) in schema.conditional {
+        let matches = obj.get(field).and_then(|v| v.as_string()) == Some(value);
+        if matches && !obj.contains_key(needed) {
+            error(errors,
+                  path,
+                  format!("missing field '{}', needed when {} is \"{}\"",
+                          needed,
+                          field,
+                          value));
+        }
+    }
+
+    for anim in schema.sprites {
+        //  Animations that can come from a sheet need one or numbered pngs
+        let sheet_allowed = fields.iter().any(|f| f.0 == anim_field(anim, "sheet"));
+        check_frames(anim, obj, sheet_allowed, path, errors);
+    }
+}
+
+fn anim_field(anim: &str, field: &str) -> String {
+    if anim.is_empty() {
+        field.to_owned()
+    } else {
+        format!("{}_{}", anim, field)
+    }
+}
+
+fn check_frames(anim: &str,
+                obj: &Object,
+                sheet_allowed: bool,
+                path: &str,
+                errors: &mut Vec<SchemaError>) {
+    let path_field = anim_field(anim, "path");
+    let sheet_field = anim_field(anim, "sheet");
+    if let Some(sheet_path) = obj.get(&sheet_field).and_then(|p| p.as_string()) {
+        check_sheet_tag(anim, obj, sheet_path, path, errors);
+        return;
+    }
+    let sprite_path = match obj.get(&path_field).and_then(|p| p.as_string()) {
+        Some(p) => p,
+        None => {
+            if sheet_allowed {
+                error(errors,
+                      path,
+                      format!("missing field '{}' or '{}'", path_field, sheet_field));
+            }
+            return;
+        }
+    };
+    let frames = obj.get(&anim_field(anim, "frames"))
+        .and_then(|f| f.as_f64())
+        .map_or(1, |f| f.max(0.0).floor() as u64);
+    for i in 1..frames + 1 {
+        let frame = format!("{}{}.png", sprite_path, i);
+        if !Path::new(&frame).exists() {
+            error(errors,
+                  &format!("{}.{}", path, path_field),
+                  format!("missing sprite frame {}", frame));
+        }
+    }
+}
+
+//  The tag named by <anim>_tag has to be one of the sheet's frameTags
+fn check_sheet_tag(anim: &str,
+                   obj: &Object,
+                   sheet_path: &str,
+                   path: &str,
+                   errors: &mut Vec<SchemaError>) {
+    let tag_field = anim_field(anim, "tag");
+    let tag = match obj.get(&tag_field).and_then(|t| t.as_string()) {
+        Some(t) => t,
+        None => return,
+    };
+    let sheet = match read_json(Path::new(sheet_path)) {
+        Ok(sheet) => sheet,
+        //  A missing sheet is reported as a missing file
+        Err(_) => return,
+    };
+    let has_tag = sheet.get("meta")
+        .and_then(|m| m.as_object())
+        .and_then(|m| m.get("frameTags"))
+        .and_then(|t| t.as_array())
+        .map_or(false, |tags| {
+            tags.iter().any(|t| {
+                t.as_object()
+                    .and_then(|t| t.get("name"))
+                    .and_then(|n| n.as_string()) == Some(tag)
+            })
+        });
+    if !has_tag {
+        error(errors,
+              &format!("{}.{}", path, tag_field),
+              format!("{} has no tag \"{}\"", sheet_path, tag));
+    }
+}
+
+fn check_value(kind: &Kind,
+               value: &Json,
+               path: &str,
+               errors: &mut Vec<SchemaError>) {
+    let ok = match (kind, value) {
+        (&Any, _) => true,
+        (&Number, v) => v.is_number(),
+        (&Count, v) => v.as_f64().map_or(false, |f| f >= 0.0 && f.fract() == 0.0),
+        (&Bool, v) => v.is_boolean(),
+        (&Str, v) => v.is_string(),
+        (&OneOf(options), &Json::String(ref s)) => {
+            if !options.contains(&s.as_str()) {
+                error(errors,
+                      path,
+                      format!("\"{}\" is not {}", s, kind.describe()));
+            }
+            true
+        }
+        (&File, &Json::String(ref s)) => {
+            if !Path::new(s).exists() {
+                error(errors, path, format!("file {} does not exist", s));
+            }
+            true
+        }
+        (&Script, &Json::String(ref s)) => {
+            let script_path = format!("scripts/{}.lisp", s);
+            if !Path::new(&script_path).exists() {
+                error(errors, path, format!("script {} does not exist", script_path));
+            }
+            true
+        }
+        (&Object(schema), &Json::Object(ref obj)) => {
+            check_object(schema, obj, path, errors);
+            true
+        }
+        (&ArrayOf(elem_kind), &Json::Array(ref array)) => {
+            for (i, elem) in array.iter().enumerate() {
+                check_value(elem_kind, elem, &format!("{}[{}]", path, i), errors);
+            }
+            true
+        }
+        (&MapOf(value_kind), &Json::Object(ref obj)) => {
+            for (key, value) in obj.iter().filter(|&(k, _)| !k.starts_with('_')) {
+                check_value(value_kind, value, &format!("{}.{}", path, key), errors);
+            }
+            true
+        }
+        (&Tagged(tag, schemas), &Json::Object(ref obj)) => {
+            match obj.get(tag).and_then(|t| t.as_string()) {
+                Some(name) => {
+                    match schemas.iter().find(|s| s.name == name) {
+                        Some(schema) => check_object(schema, obj, path, errors),
+                        None => {
+                            error(errors,
+                                  &format!("{}.{}", path, tag),
+                                  format!("unknown {} \"{}\"", tag, name))
+                        }
+                    }
+                }
+                None => {
+                    error(errors,
+                          path,
+                          format!("missing field '{}', expected a string", tag))
+                }
+            }
+            true
+        }
+        _ => false,
+    };
+    if !ok {
+        error(errors,
+              path,
+              format!("expected {}, found {}",
+                      kind.describe(),
+                      json_type(value)));
+    }
+}
+
+//  Levenshtein distance, used to suggest fixes for misspelt fields
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..b_chars.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b_chars.iter().enumerate() {
+            let substitute = prev[j] + if ca == *cb { 0 } else { 1 };
+            cur.push(substitute.min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b_chars.len()]
+}
+
+const WEAPONS: &'static [&'static str] = &["melee", "bow"];
+
+static ANIMATOR_STATE: Schema = Schema {
+    name: "animator state",
+    base: None,
+    fields: &[Field("animation", Str, Required),
+              //  A number or the name of a parameter
+              Field("rate", Any, Optional),
+              Field("one_shot", Bool, Optional),
+              Field("then", Str, Optional),
+              Field("events", MapOf(&ArrayOf(&Count)), Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static ANIMATOR_TRANSITION: Schema = Schema {
+    name: "animator transition",
+    base: None,
+    fields: &[//  A state or list of states
+              Field("from", Any, Optional),
+              Field("to", Str, Required),
+              Field("when", MapOf(&Any), Optional),
+              Field("blend", Number, Optional),
+              Field("interrupt", Bool, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static ANIMATOR: Schema = Schema {
+    name: "animator",
+    base: None,
+    fields: &[Field("initial", Str, Required),
+              Field("states", MapOf(&Object(&ANIMATOR_STATE)), Required),
+              Field("transitions", ArrayOf(&Object(&ANIMATOR_TRANSITION)), Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static ANIMATION: Schema = Schema {
+    name: "animation",
+    base: None,
+    fields: &[Field("frames", Count, Optional),
+              Field("path", Str, Optional),
+              Field("sheet", File, Optional),
+              Field("tag", Str, Optional)],
+    sprites: &[""],
+    conditional: &[],
+};
+
+//  Animations for a scripted object
+pub static ANIMATED: Schema = Schema {
+    name: "animated",
+    base: None,
+    fields: &[Field("animations", MapOf(&Object(&ANIMATION)), Required),
+              Field("speed", Number, Optional),
+              Field("animator", Object(&ANIMATOR), Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static WORLD_CONSTANTS: Schema = Schema {
+    name: "world constants",
+    base: None,
+    fields: &[Field("gravity", Number, Required),
+              Field("gravity_down_mult", Number, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static WEAPON: Schema = Schema {
+    name: "weapon",
+    base: None,
+    fields: &[Field("weapon", OneOf(WEAPONS), Required),
+              Field("projectile", File, Optional),
+              Field("ammo", Count, Optional),
+              Field("aiming_frames", Count, Required),
+              Field("aiming_path", Str, Optional),
+              Field("aiming_sheet", File, Optional),
+              Field("aiming_tag", Str, Optional),
+              Field("firing_frames", Count, Required),
+              Field("firing_path", Str, Required)],
+    sprites: &["aiming", "firing"],
+    conditional: &[("weapon", "bow", "projectile")],
+};
+
+pub static PLAYER: Schema = Schema {
+    name: "player",
+    base: None,
+    fields: &[Field("idle_frames", Count, Optional),
+              Field("idle_path", Str, Optional),
+              Field("idle_sheet", File, Optional),
+              Field("idle_tag", Str, Optional),
+              Field("running_frames", Count, Optional),
+              Field("running_path", Str, Optional),
+              Field("running_sheet", File, Optional),
+              Field("running_tag", Str, Optional),
+              Field("jumping_frames", Count, Optional),
+              Field("jumping_path", Str, Optional),
+              Field("jumping_sheet", File, Optional),
+              Field("jumping_tag", Str, Optional),
+              Field("falling_frames", Count, Optional),
+              Field("falling_path", Str, Optional),
+              Field("falling_sheet", File, Optional),
+              Field("falling_tag", Str, Optional),
+              Field("swinging_frames", Count, Optional),
+              Field("swinging_path", Str, Optional),
+              Field("swinging_sheet", File, Optional),
+              Field("swinging_tag", Str, Optional),
+              Field("dashing_frames", Count, Optional),
+              Field("dashing_path", Str, Optional),
+              Field("dashing_sheet", File, Optional),
+              Field("dashing_tag", Str, Optional),
+              Field("weapons", ArrayOf(&Object(&WEAPON)), Required),
+              Field("fire_anim_time", Number, Optional),
+              Field("animator", Object(&ANIMATOR), Required),
+              Field("speed", Number, Required),
+              Field("scale", Number, Optional),
+              Field("width", Number, Required),
+              Field("height", Number, Required),
+              Field("start_hp", Number, Required),
+              Field("friction", Number, Required),
+              Field("friction_air_mult", Number, Optional),
+              Field("moveforce", Number, Required),
+              Field("moveforce_air_mult", Number, Optional),
+              Field("jumpforce", Number, Required),
+              Field("max_runspeed", Number, Required),
+              Field("maxspeed", Number, Required),
+              Field("dash_cd", Number, Optional),
+              Field("dash_duration", Number, Optional),
+              Field("dash_invuln", Number, Optional),
+              Field("dash_force", Number, Optional),
+              Field("jump_cd", Number, Optional),
+              Field("damage_cd", Number, Optional)],
+    sprites: &["idle", "running", "jumping", "falling", "swinging", "dashing"],
+    conditional: &[],
+};
+
+pub static GRAPPLE: Schema = Schema {
+    name: "grapple",
+    base: None,
+    fields: &[Field("extend_speed", Number, Required),
+              Field("retract_speed", Number, Required),
+              Field("retract_force", Number, Required),
+              Field("retract_epsilon", Number, Required),
+              Field("elast", Number, Required),
+              Field("damp", Number, Required),
+              Field("cd", Number, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static PROJECTILE: Schema = Schema {
+    name: "projectile",
+    base: None,
+    fields: &[Field("name", Str, Required),
+              Field("sprite_frames", Count, Optional),
+              Field("sprite_path", Str, Optional),
+              Field("sprite_speed", Number, Required),
+              Field("scale", Number, Required),
+              Field("width", Number, Required),
+              Field("height", Number, Required),
+              Field("speed", Number, Required),
+              Field("gravity_scale", Number, Required),
+              Field("damage", Number, Required),
+              Field("pierce", Count, Required),
+              Field("lifetime", Number, Required)],
+    sprites: &["sprite"],
+    conditional: &[],
+};
+
+pub static ENEMY: Schema = Schema {
+    name: "enemy",
+    base: None,
+    fields: &[Field("name", Str, Required),
+              Field("idle_frames", Count, Optional),
+              Field("idle_path", Str, Optional),
+              Field("idle_sheet", File, Optional),
+              Field("idle_tag", Str, Optional),
+              Field("running_frames", Count, Optional),
+              Field("running_path", Str, Optional),
+              Field("running_sheet", File, Optional),
+              Field("running_tag", Str, Optional),
+              Field("jumping_frames", Count, Optional),
+              Field("jumping_path", Str, Optional),
+              Field("jumping_sheet", File, Optional),
+              Field("jumping_tag", Str, Optional),
+              Field("attacking_frames", Count, Optional),
+              Field("attacking_path", Str, Optional),
+              Field("attacking_sheet", File, Optional),
+              Field("attacking_tag", Str, Optional),
+              Field("animator", Object(&ANIMATOR), Required),
+              Field("weapon", OneOf(WEAPONS), Required),
+              Field("projectile", File, Optional),
+              Field("speed", Number, Required),
+              Field("scale", Number, Optional),
+              Field("width", Number, Required),
+              Field("height", Number, Required),
+              Field("start_hp", Number, Required),
+              Field("friction", Number, Required),
+              Field("friction_air_mult", Number, Optional),
+              Field("moveforce", Number, Required),
+              Field("moveforce_air_mult", Number, Optional),
+              Field("jumpforce", Number, Required),
+              Field("max_runspeed", Number, Required),
+              Field("maxspeed", Number, Required),
+              Field("jump_cd", Number, Optional),
+              Field("damage_cd", Number, Optional),
+              Field("idle_move_chance", Number, Optional),
+              Field("idle_stop_chance", Number, Optional),
+              Field("alert_dist", Number, Optional),
+              Field("bounce_force", Number, Optional),
+              Field("dash_cd", Number, Optional),
+              Field("dash_duration", Number, Optional),
+              Field("dash_invuln", Number, Optional),
+              Field("dash_force", Number, Optional)],
+    sprites: &["idle", "running", "jumping", "attacking"],
+    conditional: &[("weapon", "bow", "projectile")],
+};
+
+pub static BOSS_ATTACK: Schema = Schema {
+    name: "boss attack",
+    base: None,
+    fields: &[Field("weapon", OneOf(WEAPONS), Required),
+              Field("projectile", File, Optional),
+              Field("cd", Number, Required)],
+    sprites: &[],
+    conditional: &[("weapon", "bow", "projectile")],
+};
+
+pub static BOSS_PHASE: Schema = Schema {
+    name: "boss phase",
+    base: None,
+    fields: &[Field("hp_fraction", Number, Required),
+              Field("behaviour",
+                    OneOf(&["chase", "keep_distance", "stationary"]),
+                    Required),
+              Field("attacks", ArrayOf(&Object(&BOSS_ATTACK)), Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static BOSS: Schema = Schema {
+    name: "boss",
+    base: None,
+    fields: &[Field("name", Str, Required),
+              Field("body", File, Required),
+              Field("hp", Number, Required),
+              Field("phases", ArrayOf(&Object(&BOSS_PHASE)), Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+//  Fields every object in a world file has
+static WORLD_OBJECT: Schema = Schema {
+    name: "world object",
+    base: None,
+    fields: &[Field("name", Str, Required),
+              Field("x", Number, Required),
+              Field("y", Number, Required),
+              Field("width", Number, Required),
+              Field("height", Number, Required),
+              Field("fields", Any, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+//  A world object with nothing beyond the shared fields
+macro_rules! plain_object {
+    ($static_name:ident, $name:expr) => {
+        static $static_name: Schema = Schema {
+            name: $name,
+            base: Some(&WORLD_OBJECT),
+            fields: &[],
+            sprites: &[],
+            conditional: &[],
+        };
+    }
+}
+
+plain_object!(OBJ_PLAYER, "player");
+plain_object!(OBJ_CLIP, "clip");
+plain_object!(OBJ_GROUND, "ground");
+plain_object!(OBJ_CROWN, "crown");
+plain_object!(OBJ_TINGE, "tinge");
+
+const BORDERS: &'static [Field] = &[Field("border_left", Bool, Required),
+                                     Field("border_right", Bool, Required),
+                                     Field("border_up", Bool, Optional),
+                                     Field("border_down", Bool, Optional)];
+
+const ENEMY_OBJECT_FIELDS: &'static [Field] = &[Field("descriptor", Str, Required),
+                                                 Field("allegiance", Count, Required)];
+
+static OBJ_ENEMY: Schema = Schema {
+    name: "enemy",
+    base: Some(&WORLD_OBJECT),
+    fields: ENEMY_OBJECT_FIELDS,
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_BLUE_ENEMY: Schema = Schema {
+    name: "blue_enemy",
+    base: Some(&WORLD_OBJECT),
+    fields: ENEMY_OBJECT_FIELDS,
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_RED_ENEMY: Schema = Schema {
+    name: "red_enemy",
+    base: Some(&WORLD_OBJECT),
+    fields: ENEMY_OBJECT_FIELDS,
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_PAGODA_BLOCK: Schema = Schema {
+    name: "pagoda_block",
+    base: Some(&WORLD_OBJECT),
+    fields: BORDERS,
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_PAGODA_GROUND: Schema = Schema {
+    name: "pagoda_ground",
+    base: Some(&WORLD_OBJECT),
+    fields: BORDERS,
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_DYN: Schema = Schema {
+    name: "dyn",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("script", Script, Required),
+              Field("connect_id", Count, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_DECOR: Schema = Schema {
+    name: "decor",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("sprite", Str, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_PICKUP: Schema = Schema {
+    name: "pickup",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("item", Str, Required), Field("amount", Count, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_DOOR: Schema = Schema {
+    name: "door",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("requires", Str, Optional),
+              Field("consume", Bool, Optional),
+              Field("connect_id", Count, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_TRIGGER: Schema = Schema {
+    name: "trigger",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("connect_target_id", Count, Required),
+              Field("requires", Str, Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_DIALOGUE: Schema = Schema {
+    name: "dialogue",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("text", Str, Required), Field("connect_id", Count, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_BOSS: Schema = Schema {
+    name: "boss",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("descriptor", File, Required),
+              Field("arena_x", Number, Required),
+              Field("arena_y", Number, Required),
+              Field("arena_width", Number, Required),
+              Field("arena_height", Number, Required),
+              Field("connect_id", Count, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static WAVE: Schema = Schema {
+    name: "wave",
+    base: None,
+    fields: &[Field("count", Count, Required),
+              Field("interval", Number, Required),
+              Field("delay", Number, Required)],
+    sprites: &[],
+    conditional: &[],
+};
+
+static OBJ_SPAWNER: Schema = Schema {
+    name: "spawner",
+    base: Some(&WORLD_OBJECT),
+    fields: &[Field("descriptor", Str, Required),
+              Field("allegiance", Count, Required),
+              Field("max_alive", Count, Required),
+              Field("spawn_chance", Number, Optional),
+              Field("connect_id", Count, Optional),
+              Field("waves", ArrayOf(&Object(&WAVE)), Optional)],
+    sprites: &[],
+    conditional: &[],
+};
+
+pub static WORLD_FILE: Schema = Schema {
+    name: "world file",
+    base: None,
+    fields: &[Field("world",
+                    ArrayOf(&Tagged("name",
+                                    &[&OBJ_PLAYER,
+                                      &OBJ_ENEMY,
+                                      &OBJ_BLUE_ENEMY,
+                                      &OBJ_RED_ENEMY,
+                                      &OBJ_DYN,
+                                      &OBJ_CLIP,
+                                      &OBJ_GROUND,
+                                      &OBJ_PAGODA_BLOCK,
+                                      &OBJ_PAGODA_GROUND,
+                                      &OBJ_DECOR,
+                                      &OBJ_CROWN,
+                                      &OBJ_PICKUP,
+                                      &OBJ_DOOR,
+                                      &OBJ_TRIGGER,
+                                      &OBJ_DIALOGUE,
+                                      &OBJ_BOSS,
+                                      &OBJ_SPAWNER,
+                                      &OBJ_TINGE])),
+                    Required)],
+    sprites: &[],
+    conditional: &[],
+};
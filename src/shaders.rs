@@ -32,6 +32,13 @@ pub fn tex_frag() -> String {
     string_from_file("shaders/tex.fs")
 }
 
+//  The two programs things are drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Program {
+    Colored,
+    Textured,
+}
+
 pub struct NoisyShader {
     obj_id: Option<u32>,
     time: f32,
@@ -99,6 +106,12 @@ impl NoisyShader {
 
         self.uniform_repl_colors_tex.set(ctx, &mat_to_opengl(self.color_morph));
     }
+    pub fn set_program(&self, program: Program, ctx: &mut GlGraphics) {
+        match program {
+            Program::Colored => self.set_colored(ctx),
+            Program::Textured => self.set_textured(ctx),
+        }
+    }
     pub fn set_color_morph_y_target(&mut self, y: fphys) {
         self.color_morph_y_target = y;
         self.color_reset_time = 10;
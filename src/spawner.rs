@@ -0,0 +1,200 @@
+/*
+ * Enemy spawners
+ *
+ * Spawners either trickle enemies out at random or run through a list of
+ * waves. The director scales how hard they push based on how long the
+ * player has survived and how much health they have left.
+ */
+
+extern crate rand;
+
+use self::rand::{Rng, thread_rng};
+use descriptors::EnemyDescriptor;
+use draw::GrphxNoDraw;
+use enemy::create_spawned as enemy_create_spawned;
+use game::*;
+use logic::*;
+use physics::PhysNone;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tools::arc_mut;
+use world::Faction;
+
+//  Seconds of play for difficulty to rise by one
+const DIRECTOR_RAMP_TIME: fphys = 120.0;
+const DIRECTOR_MAX: fphys = 3.0;
+//  Difficulty multiplier when the player is on almost no health
+const DIRECTOR_LOW_HP_MULT: fphys = 0.5;
+
+pub struct Director {
+    elapsed: fphys,
+    hp_fraction: fphys,
+}
+
+impl Director {
+    pub fn new() -> Self {
+        Director {
+            elapsed: 0.0,
+            hp_fraction: 1.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: fphys, hp: fphys, hp_max: fphys) {
+        self.elapsed += dt;
+        self.hp_fraction = if hp_max > 0.0 {
+            (hp / hp_max).max(0.0).min(1.0)
+        } else {
+            1.0
+        };
+    }
+
+    //  Multiplier applied to spawn chances and counts
+    pub fn difficulty(&self) -> fphys {
+        let time_factor = (1.0 + self.elapsed / DIRECTOR_RAMP_TIME)
+            .min(DIRECTOR_MAX);
+        let hp_factor = DIRECTOR_LOW_HP_MULT +
+                        (1.0 - DIRECTOR_LOW_HP_MULT) * self.hp_fraction;
+        time_factor * hp_factor
+    }
+}
+
+pub struct Wave {
+    pub count: u32,
+    //  Seconds between each enemy in the wave
+    pub interval: fphys,
+    //  Seconds to wait once the previous wave is cleared
+    pub delay: fphys,
+}
+
+pub struct SpawnerConfig {
+    pub descr: Rc<EnemyDescriptor>,
+    pub faction: Faction,
+    pub width: Width,
+    //  Chance to spawn each tick when not running waves
+    pub spawn_chance: fphys,
+    pub max_alive: u32,
+    pub waves: Vec<Wave>,
+    //  Wait for an MTrigger before spawning anything
+    pub on_trigger: bool,
+}
+
+struct SpawnerLogic {
+    config: SpawnerConfig,
+    pos: Pos,
+    active: bool,
+    //  Spawned enemies, and whether they have shown up in the world yet
+    alive: Vec<(Id, bool)>,
+    wave: usize,
+    wave_left: u32,
+    timer: fphys,
+    director: Arc<Mutex<Director>>,
+}
+
+impl SpawnerLogic {
+    fn spawn(&mut self, args: &LogicUpdateArgs) {
+        let mut rng = thread_rng();
+        let Pos(x, y) = self.pos;
+        let Width(w) = self.config.width;
+        let sx = if w > 0.0 { x + rng.gen_range(0.0, w) } else { x };
+        let id = args.world.generate_id();
+        let e = enemy_create_spawned(id,
+                                     Pos(sx, y),
+                                     self.config.descr.clone(),
+                                     args.world,
+                                     self.config.faction);
+        args.metabuffer.issue(MetaCommand::CreateObject(e));
+        self.alive.push((id, false));
+    }
+
+    fn max_alive(&self, difficulty: fphys) -> usize {
+        (self.config.max_alive as fphys * difficulty).ceil() as usize
+    }
+
+    fn tick_random(&mut self, difficulty: fphys, args: &LogicUpdateArgs) {
+        let mut rng = thread_rng();
+        if self.alive.len() < self.max_alive(difficulty) &&
+           rng.gen_range(0.0, 1.0) < self.config.spawn_chance * difficulty {
+            self.spawn(args);
+        }
+    }
+
+    fn tick_waves(&mut self, difficulty: fphys, args: &LogicUpdateArgs) {
+        let dt = args.piston.dt as fphys;
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return;
+        }
+
+        if self.wave_left > 0 {
+            if self.alive.len() < self.max_alive(difficulty) {
+                self.spawn(args);
+                self.wave_left -= 1;
+            }
+            self.timer = self.config.waves[self.wave - 1].interval;
+        } else if self.alive.is_empty() && self.wave < self.config.waves.len() {
+            //  Previous wave cleared, queue up the next one
+            let wave = &self.config.waves[self.wave];
+            self.wave_left = (wave.count as fphys * difficulty).ceil() as u32;
+            self.timer = wave.delay;
+            self.wave += 1;
+        }
+    }
+}
+
+impl Logical for SpawnerLogic {
+    fn tick(&mut self, args: &LogicUpdateArgs) {
+        for m in args.message_buffer.read_buffer() {
+            match m {
+                ObjMessage::MTrigger => {
+                    self.active = true;
+                }
+                ObjMessage::MDescriptorsReloaded(reloaded) => {
+                    //  Only affects enemies spawned from now on
+                    if let Some(descr) = self.config.descr.reloaded(&reloaded) {
+                        self.config.descr = descr;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !self.active {
+            return;
+        }
+
+        //  Forget enemies that have been removed from the world. New enemies
+        //  take a tick to appear so only drop ones that have been seen.
+        for &mut (id, ref mut seen) in &mut self.alive {
+            if args.world.get(id).is_some() {
+                *seen = true;
+            }
+        }
+        self.alive.retain(|&(id, seen)| !seen || args.world.get(id).is_some());
+
+        let difficulty = self.director.lock().unwrap().difficulty();
+        if self.config.waves.is_empty() {
+            self.tick_random(difficulty, args);
+        } else {
+            self.tick_waves(difficulty, args);
+        }
+    }
+}
+
+pub fn create(id: Id,
+              pos: Pos,
+              config: SpawnerConfig,
+              director: Arc<Mutex<Director>>)
+              -> GameObj {
+    let g = arc_mut(GrphxNoDraw {});
+    let p = arc_mut(PhysNone { id: id });
+    let l = arc_mut(SpawnerLogic {
+        active: !config.on_trigger,
+        config: config,
+        pos: pos,
+        alive: Vec::new(),
+        wave: 0,
+        wave_left: 0,
+        timer: 0.0,
+        director: director,
+    });
+    GameObj::new(id, "spawner".to_owned(), g, p, l)
+}
@@ -0,0 +1,354 @@
+use atlas::AtlasSprite;
+use draw::SpriteBatch;
+use game::fphys;
+use graphics::{DrawState, Image, ImageSize, Transformed};
+use graphics::math::Matrix2d;
+use graphics::types::Color;
+use opengl_graphics::{Filter, GlGraphics, Texture};
+use piston_window::TextureSettings;
+use rustc_serialize::json::{Json, Object};
+use schema::read_json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/*
+ * Animations are either numbered pngs (idle1.png, idle2.png..) or frames of
+ * a sprite sheet exported from Aseprite or TexturePacker as json.
+ *
+ * A sheet's "frames" is a hash or array of frames, each with its "frame"
+ * rect in the atlas and an optional "duration" in milliseconds. "meta"
+ * gives the atlas "image" next to the json, Aseprite "frameTags" naming
+ * runs of frames as animations, and "slices" whose pivot is used for
+ * rotation. TexturePacker's per frame "pivot" is also understood.
+ */
+
+pub struct Frame {
+    //  Index into the sheet's textures
+    pub texture: usize,
+    //  [x, y, w, h] of the frame in its texture
+    pub src: [f64; 4],
+    //  Where a trimmed frame sits inside its untrimmed size
+    pub offset: (fphys, fphys),
+    pub source_size: (fphys, fphys),
+    //  Point the frame rotates about, in untrimmed pixels
+    pub pivot: (fphys, fphys),
+    //  Seconds
+    pub duration: fphys,
+}
+
+pub struct SpriteSheet {
+    //  Atlas pages are shared, so several sheets may hold the same texture
+    pub textures: Vec<Rc<Texture>>,
+    pub frames: Vec<Frame>,
+    //  Frame indices in play order for each tag
+    pub tags: HashMap<String, Vec<usize>>,
+}
+
+//  A run of frames from a sheet, looped
+pub struct Animation {
+    pub sheet: Rc<SpriteSheet>,
+    pub frames: Vec<usize>,
+    pub length: fphys,
+}
+
+//  Sheets already loaded by a descriptor, so tags share one atlas
+pub type SheetCache = HashMap<String, Rc<SpriteSheet>>;
+
+//  Source rect covering the whole texture
+pub fn full_rect(texture: &Texture) -> [f64; 4] {
+    [0.0, 0.0, texture.get_width() as f64, texture.get_height() as f64]
+}
+
+pub fn texture_settings() -> TextureSettings {
+    let mut ts = TextureSettings::new();
+    ts.set_mag(Filter::Nearest);
+    ts
+}
+
+fn get_f(obj: &Object, field: &str) -> Option<fphys> {
+    obj.get(field).and_then(|x| x.as_f64())
+}
+
+fn get_rect(obj: &Object, field: &str) -> Option<[f64; 4]> {
+    let r = obj.get(field)?.as_object()?;
+    Some([get_f(r, "x").unwrap_or(0.0),
+          get_f(r, "y").unwrap_or(0.0),
+          get_f(r, "w")?,
+          get_f(r, "h")?])
+}
+
+//  Sort "idle 2.ase" before "idle 10.ase" in hash exports
+fn natural_key(name: &str) -> (String, u64) {
+    let end = name.rfind(|c: char| c.is_digit(10)).map_or(0, |i| i + 1);
+    let start = name[..end]
+        .rfind(|c: char| !c.is_digit(10))
+        .map_or(0, |i| i + 1);
+    let number = name[start..end].parse().unwrap_or(0);
+    (format!("{}{}", &name[..start], &name[end..]), number)
+}
+
+fn parse_frame(name: &str, obj: &Object) -> Result<Frame, String> {
+    let src = get_rect(obj, "frame")
+        .ok_or(format!("frame {} has no rect", name))?;
+    let (offset, source_size) = match (get_rect(obj, "spriteSourceSize"),
+                                       obj.get("sourceSize").and_then(|s| s.as_object())) {
+        (Some(sss), Some(ss)) => {
+            ((sss[0], sss[1]),
+             (get_f(ss, "w").unwrap_or(src[2]), get_f(ss, "h").unwrap_or(src[3])))
+        }
+        _ => ((0.0, 0.0), (src[2], src[3])),
+    };
+    if obj.get("rotated").and_then(|r| r.as_boolean()) == Some(true) {
+        return Err(format!("frame {} is rotated, export without rotation", name));
+    }
+    //  TexturePacker pivots are fractions of the untrimmed size
+    let pivot = obj.get("pivot")
+        .and_then(|p| p.as_object())
+        .map_or((source_size.0 / 2.0, source_size.1 / 2.0), |p| {
+            (get_f(p, "x").unwrap_or(0.5) * source_size.0,
+             get_f(p, "y").unwrap_or(0.5) * source_size.1)
+        });
+    Ok(Frame {
+        texture: 0,
+        src: src,
+        offset: offset,
+        source_size: source_size,
+        pivot: pivot,
+        duration: get_f(obj, "duration").unwrap_or(100.0) / 1000.0,
+    })
+}
+
+//  Frame indices for an Aseprite tag, expanding its direction
+fn tag_frames(tag: &Object, frame_count: usize) -> Option<(String, Vec<usize>)> {
+    let name = tag.get("name")?.as_string()?.to_owned();
+    let from = get_f(tag, "from")? as usize;
+    let to = (get_f(tag, "to")? as usize).min(frame_count.saturating_sub(1));
+    let forward = (from..to + 1).collect::<Vec<usize>>();
+    let frames = match tag.get("direction").and_then(|d| d.as_string()) {
+        Some("reverse") => forward.into_iter().rev().collect(),
+        Some("pingpong") => {
+            let back = forward.iter().rev().skip(1).take(forward.len().saturating_sub(2));
+            forward.iter().chain(back).cloned().collect()
+        }
+        _ => forward,
+    };
+    Some((name, frames))
+}
+
+impl SpriteSheet {
+    pub fn load(json_path: &Path) -> Result<Self, String> {
+        let obj = read_json(json_path)?;
+        let empty = Object::new();
+        let meta = obj.get("meta").and_then(|m| m.as_object()).unwrap_or(&empty);
+
+        let mut frames = Vec::new();
+        match obj.get("frames") {
+            Some(&Json::Object(ref hash)) => {
+                let mut named = hash.iter().collect::<Vec<_>>();
+                named.sort_by_key(|&(name, _)| natural_key(name));
+                for (name, frame) in named {
+                    let frame = frame.as_object()
+                        .ok_or(format!("frame {} is not an object", name))?;
+                    frames.push(parse_frame(name, frame)?);
+                }
+            }
+            Some(&Json::Array(ref array)) => {
+                for (i, frame) in array.iter().enumerate() {
+                    let frame = frame.as_object()
+                        .ok_or(format!("frame {} is not an object", i))?;
+                    let name = frame.get("filename")
+                        .and_then(|f| f.as_string())
+                        .map_or(i.to_string(), |f| f.to_owned());
+                    frames.push(parse_frame(&name, frame)?);
+                }
+            }
+            _ => return Err(format!("{:?} has no frames", json_path)),
+        }
+        if frames.is_empty() {
+            return Err(format!("{:?} has no frames", json_path));
+        }
+
+        //  An Aseprite slice pivot applies to every frame
+        let slice_pivot = meta.get("slices")
+            .and_then(|s| s.as_array())
+            .and_then(|slices| {
+                slices.iter()
+                    .filter_map(|s| s.as_object())
+                    .filter_map(|s| s.get("keys").and_then(|k| k.as_array()))
+                    .filter_map(|keys| keys.first().and_then(|k| k.as_object()))
+                    .filter_map(|key| key.get("pivot").and_then(|p| p.as_object()))
+                    .next()
+                    .and_then(|p| Some((get_f(p, "x")?, get_f(p, "y")?)))
+            });
+        if let Some(pivot) = slice_pivot {
+            for frame in &mut frames {
+                frame.pivot = pivot;
+            }
+        }
+
+        let mut tags = HashMap::new();
+        for tag in meta.get("frameTags")
+            .and_then(|t| t.as_array())
+            .map_or(&[][..], |t| &t[..]) {
+            if let Some((name, indices)) = tag.as_object()
+                .and_then(|t| tag_frames(t, frames.len())) {
+                tags.insert(name, indices);
+            }
+        }
+
+        let image_path = match meta.get("image").and_then(|i| i.as_string()) {
+            Some(image) => json_path.with_file_name(image),
+            None => json_path.with_extension("png"),
+        };
+        let texture = Texture::from_path_settings(&image_path, &texture_settings())
+            .map_err(|e| format!("could not load {:?}: {}", image_path, e))?;
+
+        Ok(SpriteSheet {
+            textures: vec![Rc::new(texture)],
+            frames: frames,
+            tags: tags,
+        })
+    }
+
+    //  Separate images each shown for duration seconds, one frame per sprite
+    pub fn from_sprites(sprites: Vec<AtlasSprite>, duration: fphys) -> Self {
+        let frames = sprites.iter()
+            .enumerate()
+            .map(|(i, sprite)| {
+                let src = sprite.src;
+                let (w, h) = (src[2], src[3]);
+                Frame {
+                    texture: i,
+                    src: src,
+                    offset: (0.0, 0.0),
+                    source_size: (w, h),
+                    pivot: (w / 2.0, h / 2.0),
+                    duration: duration,
+                }
+            })
+            .collect();
+        SpriteSheet {
+            textures: sprites.into_iter().map(|s| s.texture).collect(),
+            frames: frames,
+            tags: HashMap::new(),
+        }
+    }
+
+    //  The frames of tag, or every frame if tag is None
+    pub fn animation(sheet: &Rc<SpriteSheet>, tag: Option<&str>) -> Result<Animation, String> {
+        let frames = match tag {
+            Some(t) => {
+                sheet.tags
+                    .get(t)
+                    .cloned()
+                    .ok_or(format!("sheet has no tag {}", t))?
+            }
+            None => (0..sheet.frames.len()).collect(),
+        };
+        Ok(Animation::new(sheet.clone(), frames))
+    }
+}
+
+//  Load the sheet at path once per cache
+pub fn cached_sheet(cache: &mut SheetCache, path: &str) -> Result<Rc<SpriteSheet>, String> {
+    if let Some(sheet) = cache.get(path) {
+        return Ok(sheet.clone());
+    }
+    let sheet = Rc::new(SpriteSheet::load(Path::new(path))?);
+    cache.insert(path.to_owned(), sheet.clone());
+    Ok(sheet)
+}
+
+impl Animation {
+    pub fn new(sheet: Rc<SpriteSheet>, frames: Vec<usize>) -> Self {
+        let length = frames.iter().map(|&i| sheet.frames[i].duration).sum();
+        Animation {
+            sheet: sheet,
+            frames: frames,
+            length: length,
+        }
+    }
+
+    //  Position in frames of the frame shown t seconds in, looping
+    pub fn frame_index_at(&self, t: fphys) -> usize {
+        let mut left = if self.length > 0.0 { t.max(0.0) % self.length } else { 0.0 };
+        for (index, &i) in self.frames.iter().enumerate() {
+            let duration = self.sheet.frames[i].duration;
+            if left < duration {
+                return index;
+            }
+            left -= duration;
+        }
+        self.frames.len() - 1
+    }
+
+    //  Frame shown t seconds into the animation, looping
+    pub fn frame_at(&self, t: fphys) -> &Frame {
+        &self.sheet.frames[self.frames[self.frame_index_at(t)]]
+    }
+
+    //  Untrimmed size of the first frame
+    pub fn size(&self) -> (fphys, fphys) {
+        self.sheet.frames[self.frames[0]].source_size
+    }
+
+    pub fn texture(&self, frame: &Frame) -> &Rc<Texture> {
+        &self.sheet.textures[frame.texture]
+    }
+
+    //  Draw the frame at t with its untrimmed top left at the transform's
+    //  origin, rotated by angle about the frame's pivot
+    pub fn draw(&self,
+                t: fphys,
+                angle: fphys,
+                draw_state: &DrawState,
+                transform: Matrix2d,
+                gl: &mut GlGraphics) {
+        let index = self.frame_index_at(t);
+        self.draw_frame(index, angle, [1.0; 4], draw_state, transform, gl);
+    }
+
+    //  As draw, but picking the frame by its position and tinting it
+    pub fn draw_frame(&self,
+                      index: usize,
+                      angle: fphys,
+                      color: Color,
+                      draw_state: &DrawState,
+                      transform: Matrix2d,
+                      gl: &mut GlGraphics) {
+        let (frame, transform) = self.placed(index, angle, transform);
+        Image::new_color(color)
+            .src_rect(frame.src)
+            .rect(frame_rect(frame))
+            .draw(&**self.texture(frame), draw_state, transform, gl);
+    }
+
+    //  As draw_frame, queueing into batch
+    pub fn queue_frame(&self,
+                       index: usize,
+                       angle: fphys,
+                       color: Color,
+                       transform: Matrix2d,
+                       batch: &mut SpriteBatch) {
+        let (frame, transform) = self.placed(index, angle, transform);
+        batch.push(self.texture(frame), color, frame.src, frame_rect(frame), transform);
+    }
+
+    //  The frame at index and transform rotated about its pivot
+    fn placed(&self, index: usize, angle: fphys, transform: Matrix2d) -> (&Frame, Matrix2d) {
+        let frame = &self.sheet.frames[self.frames[index.min(self.frames.len() - 1)]];
+        let (px, py) = frame.pivot;
+        let transform = if angle == 0.0 {
+            transform
+        } else {
+            transform.trans(px, py).rot_rad(angle).trans(-px, -py)
+        };
+        (frame, transform)
+    }
+}
+
+//  Where a frame is drawn relative to its untrimmed top left
+fn frame_rect(frame: &Frame) -> [f64; 4] {
+    [frame.offset.0, frame.offset.1, frame.src[2], frame.src[3]]
+}
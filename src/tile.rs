@@ -1,9 +1,10 @@
+use atlas::{AtlasSprite, load_sprite};
 use draw::{Color, Rectangle};
-use draw::{Drawable, ViewTransform};
+use draw::{Drawable, SpriteBatch, ViewTransform};
 use game::{Height, Width, Pos, fphys};
 use gen::{GhostTile, GhostTileType, TileEdge};
 use graphics::Transformed;
-use graphics::image;
+use graphics::{Image, image};
 use graphics::ImageSize;
 use opengl_graphics::{Filter, GlGraphics};
 use opengl_graphics::Texture;
@@ -12,49 +13,47 @@ use piston_window::TextureSettings;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::path::Path;
+use sprite_sheet::SpriteSheet;
 
 pub struct TileManager {
-    pub pagoda_back_left: Texture,
-    pub pagoda_back_right: Texture,
-    pub pagoda_back01: Texture,
-    pub pagoda_back02: Texture,
-    pub pagoda_roof_left: Texture,
-    pub pagoda_roof_right: Texture,
-    pub pagoda_roof: Texture,
-    pub decor: HashMap<String, Texture>,
+    pub pagoda_back_left: AtlasSprite,
+    pub pagoda_back_right: AtlasSprite,
+    pub pagoda_back01: AtlasSprite,
+    pub pagoda_back02: AtlasSprite,
+    pub pagoda_roof_left: AtlasSprite,
+    pub pagoda_roof_right: AtlasSprite,
+    pub pagoda_roof: AtlasSprite,
+    pub decor: HashMap<String, AtlasSprite>,
+    //  Sheets in the decor dir, their tags are used as "<sheet>.json:<tag>"
+    pub decor_sheets: HashMap<String, SpriteSheet>,
 }
 
 impl TileManager {
     pub fn load() -> Result<Self, String> {
         print!("Loading tile textures..");
-        let mut ts = TextureSettings::new();
-        ts.set_mag(Filter::Nearest);
-        let pagoda_back_left =
-            Texture::from_path_settings("sprites/tileL01.png", &ts)?;
-        let pagoda_back_right =
-            Texture::from_path_settings("sprites/tileR01.png", &ts)?;
-        let pagoda_back01 = Texture::from_path_settings("sprites/tile01.png",
-                                                        &ts)?;
-        let pagoda_back02 = Texture::from_path_settings("sprites/tile02.png",
-                                                        &ts)?;
-        let pagoda_roof_left =
-            Texture::from_path_settings("sprites/roofL01.png", &ts)?;
-        let pagoda_roof_right =
-            Texture::from_path_settings("sprites/roofR01.png", &ts)?;
-        let pagoda_roof = Texture::from_path_settings("sprites/roof01.png",
-                                                      &ts)?;
+        let pagoda_back_left = load_sprite("sprites/tileL01.png")?;
+        let pagoda_back_right = load_sprite("sprites/tileR01.png")?;
+        let pagoda_back01 = load_sprite("sprites/tile01.png")?;
+        let pagoda_back02 = load_sprite("sprites/tile02.png")?;
+        let pagoda_roof_left = load_sprite("sprites/roofL01.png")?;
+        let pagoda_roof_right = load_sprite("sprites/roofR01.png")?;
+        let pagoda_roof = load_sprite("sprites/roof01.png")?;
 
         // Construct map of "decor" tiles from sprites in decor dir
         let mut decor = HashMap::new();
+        let mut decor_sheets = HashMap::new();
         let decor_path = Path::new("sprites/decor");
         for file in fs::read_dir(decor_path).unwrap() {
           let f = file.unwrap();
-          let texture = Texture::from_path_settings(f.path().as_path(), &ts)?;
           let fp = f.path();
           let os_filename = fp.file_name().unwrap();
           let filename = os_filename.to_str().unwrap().to_owned();
           println!("Found decor {}", filename);
-          decor.insert(filename, texture);
+          if fp.extension().map_or(false, |e| e == "json") {
+            decor_sheets.insert(filename, SpriteSheet::load(&fp)?);
+          } else if fp.extension().map_or(false, |e| e == "png") {
+            decor.insert(filename, load_sprite(&fp)?);
+          }
         }
 
         println!("Done!");
@@ -67,6 +66,22 @@ impl TileManager {
             pagoda_roof_right: pagoda_roof_right,
             pagoda_roof: pagoda_roof,
             decor: decor,
+            decor_sheets: decor_sheets,
+        })
+    }
+
+    //  A decor png by file name, or the first frame of a tag in a decor
+    //  sheet as "<sheet>.json:<tag>"
+    fn decor_sprite(&self, name: &str) -> Option<AtlasSprite> {
+        if let Some(sprite) = self.decor.get(name) {
+            return Some(sprite.clone());
+        }
+        let mut parts = name.splitn(2, ':');
+        let sheet = self.decor_sheets.get(parts.next()?)?;
+        let frame = &sheet.frames[*sheet.tags.get(parts.next()?)?.first()?];
+        Some(AtlasSprite {
+            texture: sheet.textures[frame.texture].clone(),
+            src: frame.src,
         })
     }
     pub fn create_from_platform(&self,
@@ -76,12 +91,12 @@ impl TileManager {
                                 -> Vec<Tile> {
         let mut ret = Vec::new();
         let tile_y = y;
-        let t1: &Texture = &self.pagoda_back01;
-        ret.push(Tile::new(Pos(x, tile_y), PAGODA_TEXW, PAGODA_TEXH, t1));
+        let t1 = &self.pagoda_back01;
+        ret.push(Tile::new(Pos(x, tile_y), PAGODA_TEXW, PAGODA_TEXH, t1.clone()));
         let mut ix = x + PAGODA_BLOCKW.0;
         while ix < x + length {
-            let t: &Texture = &self.pagoda_back01;
-            ret.push(Tile::new(Pos(ix, tile_y), PAGODA_TEXW, PAGODA_TEXH, t));
+            let t = &self.pagoda_back01;
+            ret.push(Tile::new(Pos(ix, tile_y), PAGODA_TEXW, PAGODA_TEXH, t.clone()));
             ix += PAGODA_BLOCKW.0;
         }
         ret
@@ -90,27 +105,29 @@ impl TileManager {
     pub fn propogate_ghosts(&self, ghosts: Vec<GhostTile>) -> Vec<Tile> {
         ghosts.iter()
             .map(|ghost| {
-                let (texture, w , h ) = match ghost.tile_type {
+                let (sprite, w , h ) = match ghost.tile_type {
                     GhostTileType::PagodaBack(ref edge) => {
-                        (match *edge {
+                        let t = match *edge {
                             TileEdge::Left => &self.pagoda_back_left,
                             TileEdge::Center => &self.pagoda_back01,
                             TileEdge::Right => &self.pagoda_back_right,
-                        }, PAGODA_TEXW, PAGODA_TEXH)
+                        };
+                        (t.clone(), PAGODA_TEXW, PAGODA_TEXH)
                     }
                     GhostTileType::PagodaRoof(ref edge) => {
-                        (match *edge {
+                        let t = match *edge {
                             TileEdge::Left => &self.pagoda_roof_left,
                             TileEdge::Center => &self.pagoda_roof,
                             TileEdge::Right => &self.pagoda_roof_right,
-                        }, PAGODA_TEXW, PAGODA_TEXH)
+                        };
+                        (t.clone(), PAGODA_TEXW, PAGODA_TEXH)
                     }
                     GhostTileType::Decor(ref s) => {
-                        match self.decor.get(&s.to_owned()) {
-                            Some(tex) => {
-                                let w = Width(fphys::from(tex.get_width() / 2));
-                                let h = Height(fphys::from(tex.get_height() / 2));
-                                (tex, w, h)
+                        match self.decor_sprite(s) {
+                            Some(sprite) => {
+                                let w = Width((sprite.src[2] / 2.0).floor());
+                                let h = Height((sprite.src[3] / 2.0).floor());
+                                (sprite, w, h)
                             },
                             None => {
                                 panic!("Error could not find decor texture {}", s);
@@ -118,24 +135,25 @@ impl TileManager {
                         }
                     }
                 };
-                Tile::new(Pos(ghost.x, ghost.y), w, h,texture)
+                Tile::new(Pos(ghost.x, ghost.y), w, h, sprite)
             })
             .collect::<Vec<Tile>>()
     }
 }
 
 #[derive(Clone)]
-pub struct Tile<'a> {
-    pub texture: &'a Texture,
+pub struct Tile {
+    //  Usually part of an atlas page
+    pub sprite: AtlasSprite,
     pub pos: Pos,
     pub height : Height,
     pub width: Width,
 }
 
-impl<'a> Tile<'a> {
-    fn new(pos: Pos, texture_width : Width, texture_height : Height, texture: &'a Texture) -> Self {
+impl Tile {
+    fn new(pos: Pos, texture_width : Width, texture_height : Height, sprite: AtlasSprite) -> Self {
         Tile {
-            texture: texture,
+            sprite: sprite,
             pos: pos,
             width: texture_width * TILE_BASESCALE,
             height: texture_height * TILE_BASESCALE,
@@ -151,7 +169,7 @@ pub const PAGODA_TEXH: Height = Height(PAGODA_TEXH_RAW);
 pub const PAGODA_BLOCKW: Width = Width(PAGODA_TEXW_RAW * TILE_BASESCALE);
 pub const PAGODA_BLOCKH: Height = Height(PAGODA_TEXH_RAW * TILE_BASESCALE);
 
-impl<'a> Drawable for Tile<'a> {
+impl Drawable for Tile {
     fn draw(&mut self,
             args: &RenderArgs,
             ctx: &mut GlGraphics,
@@ -161,7 +179,7 @@ impl<'a> Drawable for Tile<'a> {
             let Height(h) = self.height;
             let transform = vt.transform(x, y - h, TILE_BASESCALE, TILE_BASESCALE, &c);
 
-            image(self.texture, transform, gl);
+            Image::new().src_rect(self.sprite.src).draw(&*self.sprite.texture, &c.draw_state, transform, gl);
         });
     }
     fn set_position(&mut self, _: Pos) {
@@ -181,4 +199,13 @@ impl<'a> Drawable for Tile<'a> {
         //(y + TILE_H > r.h && y < r.y + r.h)
 
     }
+
+    fn queue(&mut self, batch: &mut SpriteBatch, vt: &ViewTransform) -> bool {
+        let Pos(x, y) = self.pos;
+        let Height(h) = self.height;
+        let transform = vt.transform(x, y - h, TILE_BASESCALE, TILE_BASESCALE, &batch.context());
+        let src = self.sprite.src;
+        batch.push(&self.sprite.texture, [1.0; 4], src, [0.0, 0.0, src[2], src[3]], transform);
+        true
+    }
 }
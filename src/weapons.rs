@@ -1,21 +1,49 @@
+use ballistics::aim;
 use collision::*;
+use descriptors::ProjectileDescriptor;
 use draw::*;
 use game::*;
 use logic::*;
+use opengl_graphics::GlGraphics;
 use physics::*;
-use piston::input::UpdateArgs;
+use piston::input::{RenderArgs, UpdateArgs};
+use player_graphics::get_index;
+use std::rc::Rc;
 use tools::*;
 use world::World;
 
 pub enum Weapon {
     Melee,
-    Bow,
+    Bow(Rc<ProjectileDescriptor>),
+}
+
+impl Weapon {
+    pub fn to_wieldable(&self) -> Box<Wieldable> {
+        match *self {
+            Weapon::Melee => Box::new(Melee {}),
+            Weapon::Bow(ref projectile) => Box::new(Bow::new(projectile.clone())),
+        }
+    }
 }
 
 pub trait Wieldable {
     fn get_cd(&self) -> fphys;
     fn desired_distance(&self) -> fphys;
     fn fire(&self, Pos, Pos, &LogicUpdateArgs);
+
+    //  Time taken to fully charge, weapons that don't charge return zero
+    fn max_charge(&self) -> fphys {
+        0.0
+    }
+
+    //  Fire after being held for charge seconds
+    fn release(&self,
+               target: Pos,
+               pos: Pos,
+               _charge: fphys,
+               args: &LogicUpdateArgs) {
+        self.fire(target, pos, args);
+    }
 }
 
 pub struct Melee {}
@@ -30,7 +58,15 @@ impl Wieldable for Melee {
     fn fire(&self, _target: Pos, _pos: Pos, _args: &LogicUpdateArgs) {}
 }
 
-pub struct Bow {}
+pub struct Bow {
+    projectile: Rc<ProjectileDescriptor>,
+}
+
+impl Bow {
+    pub fn new(projectile: Rc<ProjectileDescriptor>) -> Self {
+        Bow { projectile: projectile }
+    }
+}
 
 impl Wieldable for Bow {
     fn get_cd(&self) -> fphys {
@@ -40,68 +76,183 @@ impl Wieldable for Bow {
         200.0
     }
     fn fire(&self, target: Pos, pos: Pos, args: &LogicUpdateArgs) {
-        let Pos(tx, _ty) = target;
-        let Pos(px, _py) = pos;
-        const fire_force: fphys = 500.0;
-        let force = if tx < px {
-            Force(-fire_force, -fire_force)
-        } else {
-            Force(fire_force, -fire_force)
-        };
-        let arrow = create_arrow(args.world.generate_id(),
-                                 args.id,
-                                 force,
-                                 pos,
-                                 args.world);
-        args.metabuffer.issue(MetaCommand::CreateObject(arrow));
+        self.shoot(target, pos, self.projectile.speed, args);
+    }
+    fn max_charge(&self) -> fphys {
+        BOW_CHARGE_TIME
+    }
+    fn release(&self,
+               target: Pos,
+               pos: Pos,
+               charge: fphys,
+               args: &LogicUpdateArgs) {
+        //  A partially drawn bow fires slower shots
+        let charge_prop = (charge / BOW_CHARGE_TIME).min(1.0);
+        let speed = self.projectile.speed *
+                    (BOW_MIN_CHARGE + (1.0 - BOW_MIN_CHARGE) * charge_prop);
+        self.shoot(target, pos, speed, args);
     }
 }
 
-pub fn create_arrow(id: Id,
-                    creator: Id,
-                    force: Force,
-                    pos: Pos,
-                    _world: &World)
-                    -> GameObj {
-    let w = Width(12.0);
-    let h = Height(12.0);
-    let c = [0.0, 0.5, 0.0, 1.0];
-    let g = arc_mut(GrphxRect {
+const BOW_CHARGE_TIME: fphys = 0.6;
+const BOW_MIN_CHARGE: fphys = 0.4;
+
+impl Bow {
+    fn shoot(&self, target: Pos, pos: Pos, speed: fphys, args: &LogicUpdateArgs) {
+        let gravity = args.world.descr.gravity_down *
+                      self.projectile.gravity_scale;
+        let vel = aim(pos, target, speed, gravity, args.world);
+        let projectile = create_projectile(args.world.generate_id(),
+                                           args.id,
+                                           self.projectile.clone(),
+                                           gravity,
+                                           vel,
+                                           pos);
+        args.metabuffer.issue(MetaCommand::CreateObject(projectile));
+    }
+}
+
+//  Cap on projectile speed relative to its launch speed
+const PROJECTILE_MAXSPEED_MULT: fphys = 2.0;
+
+pub fn create_projectile(id: Id,
+                         creator: Id,
+                         descr: Rc<ProjectileDescriptor>,
+                         gravity: fphys,
+                         vel: Vel,
+                         pos: Pos)
+                         -> GameObj {
+    let w = descr.width * descr.scale;
+    let h = descr.height * descr.scale;
+    let g = arc_mut(ProjectileGphx {
         pos: pos,
-        w: w,
-        h: h,
-        color: c,
+        descr: descr.clone(),
+        frame: 0.0,
     });
     let props = BBProperties {
         id: id,
         owner_type: BBOwnerType::PLAYER_ENTITY | BBOwnerType::DAMAGE | BBOwnerType::NOCOLLIDE,
     };
-    let mut phys =
-        PhysDyn::new(props, pos, Mass(1.0), 100.0, w, h, true, g.clone());
-    phys.apply_force(force);
+    let mut phys = PhysDyn::new(props,
+                                pos,
+                                Mass(1.0),
+                                descr.speed * PROJECTILE_MAXSPEED_MULT,
+                                w,
+                                h,
+                                true,
+                                g.clone());
+    phys.set_velocity(vel);
     phys.collide_with = BBOwnerType::BLOCK;
     let p = arc_mut(phys);
-    let l = arc_mut(ArrowLogic { creator: creator });
-    GameObj::new(id, "arrow".to_owned(), g, p, l)
+    let name = descr.name.clone();
+    let l = arc_mut(ProjectileLogic {
+        creator: creator,
+        gravity: gravity,
+        time_left: descr.lifetime,
+        hit: Vec::new(),
+        descr: descr,
+    });
+    GameObj::new(id, name, g, p, l)
 }
 
-struct ArrowLogic {
+struct ProjectileLogic {
     creator: Id,
+    gravity: fphys,
+    time_left: fphys,
+    //  Fighters already damaged, so piercing projectiles only hit once
+    hit: Vec<Id>,
+    descr: Rc<ProjectileDescriptor>,
 }
 
-impl Logical for ArrowLogic {
+impl Logical for ProjectileLogic {
     fn tick(&mut self, args: &LogicUpdateArgs) {
+        self.time_left -= args.piston.dt;
+        if self.time_left <= 0.0 {
+            args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
+            return;
+        }
+
         for m in args.message_buffer.read_buffer() {
             if let ObjMessage::MCollision(c) = m {
-                if c.other_type.contains(BBOwnerType::PLAYER) ||
-                   c.other_type.contains(BBOwnerType::ENEMY) &&
-                   c.other_id != self.creator ||
-                   c.other_type.contains(BBOwnerType::BLOCK) {
-                    args.metabuffer.issue(MetaCommand::RemoveObject(args.id))
+                if c.other_type.contains(BBOwnerType::BLOCK) {
+                    args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
+                    return;
+                }
+                let fighter = c.other_type.contains(BBOwnerType::PLAYER) ||
+                              c.other_type.contains(BBOwnerType::ENEMY);
+                if fighter && c.other_id != self.creator &&
+                   !self.hit.contains(&c.other_id) {
+                    self.hit.push(c.other_id);
+                    args.metabuffer.mess_obj(c.other_id,
+                                             ObjMessage::MDamage(self.descr.damage));
+                    if self.hit.len() as u32 > self.descr.pierce {
+                        args.metabuffer.issue(MetaCommand::RemoveObject(args.id));
+                        return;
+                    }
                 }
             }
         }
         args.metabuffer
-            .issue(MetaCommand::ApplyForce(args.id, Force(0.0, GRAVITY_DOWN)));
+            .issue(MetaCommand::ApplyForce(args.id, Force(0.0, self.gravity)));
+    }
+}
+
+pub struct ProjectileGphx {
+    pub pos: Pos,
+    pub descr: Rc<ProjectileDescriptor>,
+    pub frame: fphys,
+}
+
+impl Drawable for ProjectileGphx {
+    fn draw(&mut self,
+            args: &RenderArgs,
+            ctx: &mut GlGraphics,
+            vt: &ViewTransform) {
+        use graphics::*;
+        let Pos(x, y) = self.pos;
+        let scale = self.descr.scale;
+        if self.descr.sprite.is_empty() {
+            let Width(w) = self.descr.width;
+            let Height(h) = self.descr.height;
+            let r = [0.0, 0.0, w, h];
+            ctx.draw(args.viewport(), |c, gl| {
+                let transform = vt.transform(x, y, scale, scale, &c);
+                rectangle(PROJECTILE_COLOR, r, transform, gl);
+            });
+        } else {
+            self.frame += 1.0;
+            let sprite = get_index(self.frame,
+                                   &self.descr.sprite,
+                                   self.descr.sprite_speed);
+            ctx.draw(args.viewport(), |c, gl| {
+                let transform = vt.transform(x, y, scale, scale, &c);
+                Image::new().src_rect(sprite.src).draw(&*sprite.texture, &c.draw_state, transform, gl);
+            });
+        }
+    }
+    fn set_position(&mut self, p: Pos) {
+        self.pos = p;
+    }
+    fn set_color(&mut self, _color: Color) {}
+    fn should_draw(&self, _: &Rectangle) -> bool {
+        true
+    }
+    fn queue(&mut self, batch: &mut SpriteBatch, vt: &ViewTransform) -> bool {
+        //  Plain rectangles are drawn directly
+        if self.descr.sprite.is_empty() {
+            return false;
+        }
+        self.frame += 1.0;
+        let sprite = get_index(self.frame,
+                               &self.descr.sprite,
+                               self.descr.sprite_speed);
+        let Pos(x, y) = self.pos;
+        let scale = self.descr.scale;
+        let transform = vt.transform(x, y, scale, scale, &batch.context());
+        let rect = [0.0, 0.0, sprite.src[2], sprite.src[3]];
+        batch.push(&sprite.texture, [1.0; 4], sprite.src, rect, transform);
+        true
     }
 }
+
+const PROJECTILE_COLOR: Color = [0.0, 0.5, 0.0, 1.0];
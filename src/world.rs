@@ -1,5 +1,5 @@
 use collision::*;
-use game::{Id, TriggerId};
+use game::{Id, TriggerId, fphys};
 use std::rc::Rc;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -26,6 +26,10 @@ pub struct World {
     fighter_sender: Sender<FighterSendType>,
     fighter_receiver: Receiver<FighterSendType>,
     fighter_buffer: Vec<Fighter>,
+    //  Current and max hp of anything that can be damaged, for scripts
+    health: HashMap<Id, (fphys, fphys)>,
+    health_sender: Sender<(Id, fphys, fphys)>,
+    health_receiver: Receiver<(Id, fphys, fphys)>,
     trigger_id_map: HashMap<TriggerId, Id>,
 }
 
@@ -56,6 +60,7 @@ impl World {
     pub fn new(descr : Rc<WorldDescriptor>) -> Self {
         let (tx, rx): (Sender<SendType>, Receiver<SendType>) = channel();
         let (fighter_tx, fighter_rx) = channel();
+        let (health_tx, health_rx) = channel();
         let world = HashMap::new();
         World {
             world: world,
@@ -66,6 +71,9 @@ impl World {
             fighter_sender: fighter_tx,
             fighter_receiver: fighter_rx,
             fighter_buffer: Vec::new(),
+            health: HashMap::new(),
+            health_sender: health_tx,
+            health_receiver: health_rx,
             player_id: 0,
             id_gen: Arc::new(Mutex::new(IdGen { current: 1 })),
             trigger_id_map: HashMap::new(),
@@ -79,9 +87,15 @@ impl World {
         self.sender = tx;
         self.world = HashMap::new();
         self.buffer = Vec::new();
+        self.health = HashMap::new();
         self.id_gen = Arc::new(Mutex::new(IdGen { current: id }));
     }
     pub fn update(&mut self) {
+        //  Health first so removals below drop the last report of the dead
+        for (id, hp, hp_max) in self.health_receiver.try_iter() {
+            self.health.insert(id, (hp, hp_max));
+        }
+
         //  Collect any new bounding box updates from the receiver
         for (p, maybe_bb) in self.receiver.try_iter() {
             match maybe_bb {
@@ -90,6 +104,7 @@ impl World {
                 }
                 None => {
                     self.world.remove(&p.id);
+                    self.health.remove(&p.id);
                 }
             }
         }
@@ -151,6 +166,14 @@ impl World {
         &self.fighter_buffer
     }
 
+    pub fn report_health(&self, id: Id, hp: fphys, hp_max: fphys) {
+        self.health_sender.send((id, hp, hp_max)).unwrap();
+    }
+
+    pub fn health(&self, id: Id) -> Option<(fphys, fphys)> {
+        self.health.get(&id).cloned()
+    }
+
     pub fn add_to_trigger_id_map(&mut self, trigger_id: TriggerId, id: Id) {
         self.trigger_id_map.insert(trigger_id, id);
     }
@@ -0,0 +1,17 @@
+//  Runs every script test in scripts/tests through `noise --test-scripts`,
+//  see src/dyn/harness.rs
+
+use std::process::Command;
+
+#[test]
+fn script_tests() {
+    let output = Command::new(env!("CARGO_BIN_EXE_noise"))
+        .arg("--test-scripts")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("could not run noise");
+    assert!(output.status.success(),
+            "script tests failed:\n{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr));
+}